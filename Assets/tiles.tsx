@@ -1,4 +1,58 @@
 <?xml version="1.0" encoding="UTF-8"?>
 <tileset version="1.5" tiledversion="1.7.2" name="tiles" tilewidth="16" tileheight="16" tilecount="420" columns="28">
  <image source="./Proprietary/Tiles/Tiles.png" width="448" height="240"/>
+ <tile id="205">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="206">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="207">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="233">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="234">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="235">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="261">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="262">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
+ <tile id="263">
+  <properties>
+   <property name="solid" type="bool" value="true"/>
+   <property name="water" type="bool" value="true"/>
+  </properties>
+ </tile>
 </tileset>
@@ -1,11 +1,35 @@
 use legion::*;
 use sdl2::pixels::Color;
 
+mod map;
 mod models;
 mod rendering;
 mod util;
 
-use models::components::{Gender, InputQueue, PlayerClass};
+/// How long each step the game is simulated forward by lasts
+const TIMESTEP: std::time::Duration = std::time::Duration::from_millis(50);
+/// The most steps that are run to catch up after a slow frame. Past
+/// that the game slows down rather than spending every frame catching up.
+const MAX_STEPS: u32 = 5;
+
+use map::autotile::AutotileInfo;
+use map::TileMap;
+use models::clock::WorldClock;
+use models::components::{
+    DamageQueue, DeathQueue, ExperienceQueue, Gender, InputQueue, PlayerClass, SaveQueue,
+    SkillExperienceQueue, StatusQueue, TalkQueue,
+};
+use models::crafting::RecipeInfo;
+use models::dialogue::DialogueInfo;
+use models::farming::{FarmingInfo, Farms};
+use models::fishing::FishingInfo;
+use models::loot::OpenedChests;
+use models::npc::ScheduleInfo;
+use models::prefab::PrefabInfo;
+use models::quest::QuestInfo;
+use models::shop::{ShopInfo, Shops};
+use models::skills::SkillInfo;
+use models::zone::{Destination, Transition, Zone, ZoneMap};
 use models::*;
 use rendering::*;
 use util::*;
@@ -32,46 +56,203 @@ fn main() {
     // ECS set-up
     let mut world = World::default();
     let mut resources = Resources::default();
-    // Create empty render and input queue resources
+    // Create empty render, input and event queue resources
     resources.insert::<RenderQueue>(vec![]);
     resources.insert::<InputQueue>(vec![]);
+    resources.insert::<DamageQueue>(vec![]);
+    resources.insert::<DeathQueue>(vec![]);
+    resources.insert::<ExperienceQueue>(vec![]);
+    resources.insert::<StatusQueue>(vec![]);
+    resources.insert::<TalkQueue>(vec![]);
+    resources.insert::<SkillExperienceQueue>(vec![]);
+    resources.insert::<SaveQueue>(vec![]);
 
     // Texture and SpriteSheet loading and creation
     let texture_creator = canvas.texture_creator();
     let sprite_info = SpriteInfo::from("Assets/SpriteData/sprites.ron").unwrap();
     let mut sprite_holder = SpriteHolder::from(&texture_creator, sprite_info);
 
+    // Gameplay data loading
+    resources.insert(Rng::from_time());
+    resources.insert(WorldClock::default());
+    resources.insert(AttackInfo::from("Assets/GameData/attacks.ron").unwrap());
+    resources.insert(KitInfo::from("Assets/GameData/kits.ron").unwrap());
+    resources.insert(ProgressionInfo::from("Assets/GameData/progression.ron").unwrap());
+    resources.insert(ItemInfo::from("Assets/GameData/items.ron").unwrap());
+    resources.insert(StatusInfo::from("Assets/GameData/status_effects.ron").unwrap());
+    resources.insert(LootInfo::from("Assets/GameData/loot.ron").unwrap());
+    resources.insert(DialogueInfo::from("Assets/GameData/dialogue.ron").unwrap());
+    resources.insert(QuestInfo::from("Assets/GameData/quests.ron").unwrap());
+    resources.insert(SkillInfo::from("Assets/GameData/skills.ron").unwrap());
+    resources.insert(RecipeInfo::from("Assets/GameData/recipes.ron").unwrap());
+    resources.insert(FishingInfo::from("Assets/GameData/fishing.ron").unwrap());
+    resources.insert(FarmingInfo::from("Assets/GameData/farming.ron").unwrap());
+    resources.insert(ScheduleInfo::from("Assets/GameData/schedules.ron").unwrap());
+    resources.insert(Farms::default());
+    resources.insert(OpenedChests::default());
+    let shop_info = ShopInfo::from("Assets/GameData/shops.ron").unwrap();
+    resources.insert(Shops::new(&shop_info));
+    resources.insert(shop_info);
+
+    resources.insert(AutotileInfo::from("Assets/GameData/autotile.ron").unwrap());
+    resources.insert(PrefabInfo::from("Assets/GameData/prefabs.ron").unwrap());
+
     // Create the player character;
     // this function pushes the new
     // player to the world automatically
     let _player = player::new(&mut world, (0, 0), PlayerClass::Warrior, Gender::Based);
 
-    let mut schedule = Schedule::builder()
+    // Start in the town, or in a dungeon if we're asked to with
+    // `--dungeon`, optionally followed by the seed to generate it from
+    let mut args = std::env::args().skip(1);
+    let map = match args.next().as_deref() {
+        Some("--dungeon") => ZoneMap::Dungeon(
+            args.next()
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or_else(|| Rng::from_time().next_u64()),
+        ),
+        _ => ZoneMap::Tiled(String::from("Assets/map.tmx")),
+    };
+    resources.insert(Zone::new(map.clone()));
+    zone::enter(
+        &mut world,
+        &mut resources,
+        &Destination {
+            map,
+            entrance: None,
+        },
+    )
+    .unwrap();
+
+    // Everything that moves the game along runs once per timestep
+    let mut update = Schedule::builder()
+        .add_system(clock::systems::tick_clock_system())
+        .add_system(farming::systems::grow_crops_system())
+        .add_system(npc::systems::follow_schedules_system())
+        // Players who are saving or loading, shopping, crafting, talking to
+        // someone, fishing or looking at their skills can't do anything else
+        .add_system(save::systems::use_save_menu_system())
+        .add_system(shop::systems::trade_system())
+        .add_system(crafting::systems::craft_system())
+        .add_system(dialogue::systems::converse_system())
+        .add_system(fishing::systems::fish_system())
+        .add_system(skills::systems::view_skills_system())
+        .add_system(dialogue::systems::talk_to_npcs_system())
+        .add_system(fishing::systems::cast_line_system())
+        // Ripe crops are harvested before the soil under them gets another look
+        .add_system(farming::systems::harvest_crops_system())
+        .add_system(farming::systems::tend_farms_system())
+        .add_system(crafting::systems::use_stations_system())
+        // The inventory screen takes all the input while it's open
+        .add_system(inventory::systems::manage_inventory_system())
+        // Chests take a pick up request before items on the ground get a look in
+        .add_system(loot::systems::open_chests_system())
+        .add_system(inventory::systems::pick_up_items_system())
+        .add_system(loot::systems::collect_gold_system())
+        .add_system(loot::systems::despawn_system())
+        .add_system(shop::systems::restock_shops_system())
+        .add_system(status::systems::tick_status_effects_system())
+        .add_system(stats::systems::calculate_stats_system())
         .add_system(player::systems::player_input_system())
-        .add_system(player::systems::animate_player_system())
+        .add_system(zone::systems::use_portals_system())
+        .add_system(abilities::systems::use_abilities_system())
+        .add_system(enemy::systems::think_system())
+        .add_system(combat::systems::melee_system())
+        // Hitboxes spawned by attacks this frame need to exist before they're resolved
+        .flush()
+        .add_system(combat::systems::resolve_hits_system())
+        .add_system(projectile::systems::simulate_projectiles_system())
+        // Effects go on before damage is dealt, while the targets of
+        // this frame's hits are still vulnerable
+        .add_system(status::systems::apply_status_effects_system())
+        .add_system(combat::systems::apply_damage_system())
+        .add_system(progression::systems::award_kill_experience_system())
+        .add_system(loot::systems::drop_loot_on_death_system())
+        .add_system(quest::systems::track_quests_system())
+        .add_system(progression::systems::apply_experience_system())
+        .add_system(skills::systems::apply_skill_experience_system())
+        .add_system(combat::systems::tick_invulnerability_system())
+        .add_system(combat::systems::recover_system())
+        .add_system(enemy::systems::recover_system())
+        .build();
+
+    // Everything that draws runs once per frame
+    let mut draw = Schedule::builder()
+        // The render queue is drawn back to front, so the fade goes first to end up on
+        // top, and the HUD and menus go before the daylight tint so they aren't tinted
+        .add_system(zone::systems::draw_transition_system())
+        .add_system(status::systems::draw_status_icons_system())
         .add_system(player::systems::draw_hud_system())
+        .add_system(loot::systems::draw_gold_system())
+        .add_system(quest::systems::draw_quest_tracker_system())
+        .add_system(progression::systems::draw_level_up_system())
+        .add_system(abilities::systems::draw_hotbar_system())
+        .add_system(inventory::systems::draw_inventory_system())
+        .add_system(dialogue::systems::draw_dialogue_system())
+        .add_system(shop::systems::draw_shop_system())
+        .add_system(crafting::systems::draw_crafting_system())
+        .add_system(skills::systems::draw_skills_system())
+        .add_system(save::systems::draw_save_menu_system())
+        .add_system(clock::systems::draw_clock_system())
+        .add_system(clock::systems::draw_daylight_system())
+        .add_system(prefab::systems::draw_static_sprites_system())
+        .add_system(prefab::systems::animate_directional_sprites_system())
+        .add_system(player::systems::animate_player_system())
+        .add_system(projectile::systems::draw_projectiles_system())
+        .add_system(fishing::systems::draw_fishing_system())
         .build();
 
-    let mut then: std::time::Instant;
-    let mut now: std::time::Instant;
+    let registry = snapshot::registry();
+
+    let mut previous = std::time::Instant::now();
+    let mut lag = std::time::Duration::ZERO;
 
     'running: loop {
+        let now = std::time::Instant::now();
+        lag += now - previous;
+        previous = now;
+
+        // Step the game forward by however many timesteps have gone by
+        let mut steps = 0;
+        while lag >= TIMESTEP && steps < MAX_STEPS {
+            update.execute(&mut world, &mut resources);
+            lag -= TIMESTEP;
+            steps += 1;
+
+            // Once the screen has faded out, move on to the next zone,
+            // saving the game on the way in
+            let destination = resources.get::<Zone>().unwrap().ready_to_leave().cloned();
+            if let Some(destination) = destination {
+                match zone::enter(&mut world, &mut resources, &destination) {
+                    Ok(()) => save::autosave(&world, &resources),
+                    // A map that won't load leaves us where we were
+                    Err(error) => {
+                        println!("Couldn't enter {:?}: {}", destination.map, error);
+                        resources.get_mut::<Zone>().unwrap().transition = Transition::None;
+                    }
+                }
+            }
+
+            save::handle_requests(&mut world, &mut resources);
+        }
+        if steps == MAX_STEPS {
+            lag = std::time::Duration::ZERO;
+        }
+
         canvas.set_draw_color(Color::RGB(0, 0, 0));
         canvas.clear();
 
-        then = std::time::Instant::now();
-
-        // Load the tilemap file and draw it onto the canvas
+        // Draw the tilemap onto the canvas
         sprite_holder
             .get_sprite("tiles")
-            .draw_map(&mut canvas, "Assets/map.tmx");
+            .draw_map(&mut canvas, &resources.get::<TileMap>().unwrap());
 
-        // Run all the systems
-        schedule.execute(&mut world, &mut resources);
+        draw.execute(&mut world, &mut resources);
 
         // Handle quit events, then pass event pump
         // to an input handler function
         let mut event_pump = sdl_ctx.event_pump().unwrap();
+        let mut pressed = vec![];
         for event in event_pump.poll_iter() {
             use sdl2::event::Event;
 
@@ -83,6 +264,43 @@ fn main() {
                 } => {
                     break 'running;
                 }
+                // Dump the whole world for debugging
+                Event::KeyDown {
+                    scancode: Some(sdl2::keyboard::Scancode::F12),
+                    repeat: false,
+                    ..
+                } => match snapshot::dump(&world, &registry) {
+                    Ok(dump) => match std::fs::write("world.ron", dump) {
+                        Ok(()) => println!("Dumped the world to world.ron"),
+                        Err(error) => println!("Couldn't write world.ron: {}", error),
+                    },
+                    Err(error) => println!("Couldn't dump the world: {}", error),
+                },
+                // And put it back the way it was when it was dumped
+                Event::KeyDown {
+                    scancode: Some(sdl2::keyboard::Scancode::F11),
+                    repeat: false,
+                    ..
+                } => {
+                    let loaded = std::fs::read_to_string("world.ron")
+                        .map_err(|error| error.to_string())
+                        .and_then(|dump| {
+                            snapshot::load(&dump, &registry).map_err(|error| error.to_string())
+                        });
+                    match loaded {
+                        Ok(loaded) => world = loaded,
+                        Err(error) => println!("Couldn't load world.ron: {}", error),
+                    }
+                }
+                // Keep track of fresh key presses for
+                // things that should only happen once
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    repeat: false,
+                    ..
+                } => {
+                    pressed.push(scancode);
+                }
                 _ => {}
             }
         }
@@ -90,7 +308,7 @@ fn main() {
         use core::ops::DerefMut;
         let mut input_vector_ref = resources.get_mut::<InputQueue>().unwrap();
         let mut input_vector = input_vector_ref.deref_mut();
-        handle_input(&mut event_pump, &mut input_vector);
+        handle_input(&mut event_pump, &pressed, &mut input_vector);
 
         // Retrieve and dereference the render queue
         // then render everything within it
@@ -101,16 +319,20 @@ fn main() {
         // Draw to the screen
         canvas.present();
 
-        // Ensure 20fps
-        now = std::time::Instant::now();
-        if now - then < std::time::Duration::new(0, 1_000_000_000 / 20) {
-            std::thread::sleep(std::time::Duration::new(0, 1_000_000_000 / 20) - (now - then));
+        // Wait for the next timestep to come round
+        let elapsed = lag + previous.elapsed();
+        if elapsed < TIMESTEP {
+            std::thread::sleep(TIMESTEP - elapsed);
         }
     }
 }
 
 // Yes this could be nicer
-fn handle_input(event_pump: &mut sdl2::EventPump, input_vector: &mut InputQueue) {
+fn handle_input(
+    event_pump: &mut sdl2::EventPump,
+    pressed: &[sdl2::keyboard::Scancode],
+    input_vector: &mut InputQueue,
+) {
     use models::components::*;
     use sdl2::keyboard::Scancode;
 
@@ -134,13 +356,93 @@ fn handle_input(event_pump: &mut sdl2::EventPump, input_vector: &mut InputQueue)
         input_vector.push(Input::Attack);
     }
 
+    let hotbar_keys = [
+        Scancode::Num1,
+        Scancode::Num2,
+        Scancode::Num3,
+        Scancode::Num4,
+    ];
+    for (slot, key) in hotbar_keys.iter().enumerate() {
+        if kb_state.is_scancode_pressed(*key) {
+            input_vector.push(Input::Ability(slot));
+        }
+    }
+
     if kb_state.is_scancode_pressed(Scancode::Space) {
         input_vector.push(Input::Run);
     }
 
+    for key in pressed {
+        match key {
+            Scancode::F => input_vector.push(Input::PickUp),
+            Scancode::E => input_vector.push(Input::Interact),
+            Scancode::Escape | Scancode::Backspace => input_vector.push(Input::Back),
+            Scancode::I | Scancode::Tab => input_vector.push(Input::ToggleInventory),
+            Scancode::K => input_vector.push(Input::ToggleSkills),
+            Scancode::C => input_vector.push(Input::ToggleCrafting),
+            Scancode::F5 => input_vector.push(Input::SaveGame),
+            Scancode::F9 => input_vector.push(Input::LoadGame),
+            Scancode::Up | Scancode::W => input_vector.push(Input::Navigate(Direction::Up)),
+            Scancode::Down | Scancode::S => input_vector.push(Input::Navigate(Direction::Down)),
+            Scancode::Left | Scancode::A => input_vector.push(Input::Navigate(Direction::Left)),
+            Scancode::Right | Scancode::D => input_vector.push(Input::Navigate(Direction::Right)),
+            Scancode::Z | Scancode::Return => input_vector.push(Input::UseItem),
+            Scancode::X => input_vector.push(Input::DropItem),
+            _ => {}
+        }
+    }
+
     for event in event_pump.poll_iter() {
         match event {
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    type Loader<T> = fn(&'static str) -> Result<T, Box<dyn std::error::Error>>;
+
+    /// Loads a data file the way the game does, failing with why it can't be
+    fn load<T>(file: &'static str, loader: Loader<T>) -> &'static str {
+        if let Err(error) = loader(file) {
+            panic!("{} doesn't load: {}", file, error);
+        }
+        file
+    }
+
+    #[test]
+    fn every_game_data_file_loads() {
+        let loaded = [
+            load("Assets/GameData/attacks.ron", AttackInfo::from),
+            load("Assets/GameData/kits.ron", KitInfo::from),
+            load("Assets/GameData/progression.ron", ProgressionInfo::from),
+            load("Assets/GameData/items.ron", ItemInfo::from),
+            load("Assets/GameData/status_effects.ron", StatusInfo::from),
+            load("Assets/GameData/loot.ron", LootInfo::from),
+            load("Assets/GameData/dialogue.ron", DialogueInfo::from),
+            load("Assets/GameData/quests.ron", QuestInfo::from),
+            load("Assets/GameData/skills.ron", SkillInfo::from),
+            load("Assets/GameData/recipes.ron", RecipeInfo::from),
+            load("Assets/GameData/fishing.ron", FishingInfo::from),
+            load("Assets/GameData/farming.ron", FarmingInfo::from),
+            load("Assets/GameData/schedules.ron", ScheduleInfo::from),
+            load("Assets/GameData/shops.ron", ShopInfo::from),
+            load("Assets/GameData/autotile.ron", AutotileInfo::from),
+            load("Assets/GameData/prefabs.ron", PrefabInfo::from),
+        ];
+
+        // A data file that isn't loaded above isn't checked at all
+        for entry in std::fs::read_dir("Assets/GameData").unwrap() {
+            let path = entry.unwrap().path();
+            assert!(
+                loaded.iter().any(|file| Path::new(file) == path),
+                "{} isn't loaded by this test",
+                path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::TileMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Bits of a tile's bitmask, each set when the neighbour
+/// on that side is the same terrain as the tile
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+
+/// How to pick the tile for one terrain
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TerrainRule {
+    /// The gid used when there's no tile for a bitmask
+    pub default: u32,
+    /// The gid for each bitmask of matching neighbours
+    #[serde(default)]
+    pub tiles: HashMap<u8, u32>,
+    /// Other terrains that count as this one when working out the
+    /// bitmask, so that e.g. stairs don't put edges on the floor around them
+    #[serde(default)]
+    pub joins: Vec<String>,
+    #[serde(default)]
+    pub solid: bool,
+}
+
+impl TerrainRule {
+    fn gids(&self) -> impl Iterator<Item = &u32> {
+        std::iter::once(&self.default).chain(self.tiles.values())
+    }
+}
+
+/// Rules for turning terrain into tiles, by terrain name
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AutotileInfo {
+    pub terrains: HashMap<String, TerrainRule>,
+}
+
+impl AutotileInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let autotile_info = ron::from_str(&contents)?;
+        Ok(autotile_info)
+    }
+
+    /// The terrain a gid belongs to, if any rule uses it
+    pub fn terrain_of(&self, gid: u32) -> Option<&str> {
+        self.terrains
+            .iter()
+            .find(|(_, rule)| rule.gids().any(|rule_gid| *rule_gid == gid))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The gids of every tile belonging to a solid terrain
+    pub fn solid_tiles(&self) -> HashSet<u32> {
+        self.terrains
+            .values()
+            .filter(|rule| rule.solid)
+            .flat_map(|rule| rule.gids().copied())
+            .collect()
+    }
+
+    /// Picks the gid for a tile of `terrain` at (x, y), given
+    /// a way of looking up the terrain of its neighbours
+    pub fn gid<'a>(
+        &self,
+        terrain: &str,
+        x: i32,
+        y: i32,
+        terrain_at: impl Fn(i32, i32) -> Option<&'a str>,
+    ) -> Option<u32> {
+        let rule = self.terrains.get(terrain)?;
+        let matches = |x: i32, y: i32| match terrain_at(x, y) {
+            Some(neighbour) => neighbour == terrain || rule.joins.iter().any(|j| j == neighbour),
+            None => false,
+        };
+
+        let mut mask = 0;
+        for (bit, dx, dy) in [(NORTH, 0, -1), (EAST, 1, 0), (SOUTH, 0, 1), (WEST, -1, 0)].iter() {
+            if matches(x + dx, y + dy) {
+                mask |= bit;
+            }
+        }
+
+        Some(*rule.tiles.get(&mask).unwrap_or(&rule.default))
+    }
+
+    /// Turns a grid of terrain names, indexed as `grid[y][x]`, into a
+    /// layer of gids. Terrain without a rule becomes an empty tile.
+    pub fn apply(&self, grid: &[Vec<&str>]) -> Vec<Vec<u32>> {
+        // Negative coordinates wrap around to huge ones, which are off the grid too
+        let terrain_at = |x: i32, y: i32| {
+            grid.get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .copied()
+        };
+
+        grid.iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, terrain)| {
+                        self.gid(terrain, x as i32, y as i32, terrain_at)
+                            .unwrap_or(0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Changes the terrain of a tile in a map that's already built, fixing
+    /// up the tiles around it so their edges still line up. Neighbours whose
+    /// gids don't belong to any terrain are left alone.
+    pub fn paint(&self, map: &mut TileMap, layer: usize, x: i32, y: i32, terrain: &str) {
+        let rule = match self.terrains.get(terrain) {
+            Some(rule) => rule,
+            None => return,
+        };
+        if rule.solid {
+            map.solid_tiles.extend(rule.gids());
+        }
+        map.set_tile(layer, x, y, rule.default);
+
+        for (tile_x, tile_y) in [(x, y), (x, y - 1), (x + 1, y), (x, y + 1), (x - 1, y)].iter() {
+            let gid = {
+                let terrain_at =
+                    |x: i32, y: i32| map.tile(layer, x, y).and_then(|gid| self.terrain_of(gid));
+                terrain_at(*tile_x, *tile_y)
+                    .and_then(|terrain| self.gid(terrain, *tile_x, *tile_y, terrain_at))
+            };
+
+            if let Some(gid) = gid {
+                map.set_tile(layer, *tile_x, *tile_y, gid);
+            }
+        }
+    }
+}
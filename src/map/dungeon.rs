@@ -0,0 +1,211 @@
+use super::autotile::AutotileInfo;
+use super::{MapObject, TileMap};
+use crate::util::Rng;
+
+/// The names of the terrains dungeons are made of,
+/// which are turned into tiles by the autotile rules
+const WALL: &str = "wall";
+const FLOOR: &str = "floor";
+const STAIRS: &str = "stairs";
+
+/// How wide corridors are, in tiles. Anything narrower than
+/// three tiles has no room for a floor tile between its edges.
+const CORRIDOR_WIDTH: i32 = 3;
+
+/// The knobs that control what the generator produces
+#[derive(Clone, Debug)]
+pub struct DungeonSettings {
+    /// The width and height of the dungeon, in tiles
+    pub dimensions: (u32, u32),
+    pub tile_dimensions: (u32, u32),
+    /// How many times the generator tries to place a room. Rooms that
+    /// would overlap another are thrown away, so dungeons usually end up
+    /// with fewer rooms than this.
+    pub room_attempts: u32,
+    /// The smallest and largest width or height of a room, in tiles
+    pub room_size: (i32, i32),
+    /// The fewest and most enemies spawned in each room
+    pub enemies_per_room: (i32, i32),
+    /// The prefabs enemies are picked from
+    pub enemy_prefabs: Vec<String>,
+    /// The map the stairs lead to, and the entrance within it
+    pub exit_map: String,
+    pub exit_entrance: String,
+}
+
+impl Default for DungeonSettings {
+    fn default() -> Self {
+        DungeonSettings {
+            dimensions: (63, 63),
+            tile_dimensions: (16, 16),
+            room_attempts: 40,
+            room_size: (5, 11),
+            enemies_per_room: (0, 3),
+            enemy_prefabs: vec![String::from("skeleton"), String::from("skeleton_archer")],
+            exit_map: String::from("Assets/map.tmx"),
+            exit_entrance: String::from("dungeon_stairs"),
+        }
+    }
+}
+
+/// A rectangular room, in tiles
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Room {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Room {
+    fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// Whether the rooms overlap or sit close enough to share a wall
+    fn too_close(&self, other: &Room) -> bool {
+        let margin = 2;
+        self.x - margin < other.x + other.width
+            && other.x - margin < self.x + self.width
+            && self.y - margin < other.y + other.height
+            && other.y - margin < self.y + self.height
+    }
+}
+
+fn carve(terrain: &mut [Vec<&str>], x: i32, y: i32, width: i32, height: i32) {
+    for row in terrain.iter_mut().skip(y as usize).take(height as usize) {
+        for tile in row.iter_mut().skip(x as usize).take(width as usize) {
+            *tile = FLOOR;
+        }
+    }
+}
+
+/// Carves an L-shaped corridor between two points, going
+/// horizontally first and then vertically
+fn carve_corridor(terrain: &mut [Vec<&str>], from: (i32, i32), to: (i32, i32)) {
+    let offset = CORRIDOR_WIDTH / 2;
+    let (left, right) = (from.0.min(to.0), from.0.max(to.0));
+    let (top, bottom) = (from.1.min(to.1), from.1.max(to.1));
+
+    carve(
+        terrain,
+        left - offset,
+        from.1 - offset,
+        right - left + CORRIDOR_WIDTH,
+        CORRIDOR_WIDTH,
+    );
+    carve(
+        terrain,
+        to.0 - offset,
+        top - offset,
+        CORRIDOR_WIDTH,
+        bottom - top + CORRIDOR_WIDTH,
+    );
+}
+
+fn object(name: &str, kind: &str, x: i32, y: i32, tile_dimensions: (u32, u32)) -> MapObject {
+    let (tile_width, tile_height) = tile_dimensions;
+    MapObject {
+        name: String::from(name),
+        kind: String::from(kind),
+        x: x * tile_width as i32,
+        y: y * tile_height as i32,
+        width: tile_width,
+        height: tile_height,
+        properties: tiled::Properties::new(),
+    }
+}
+
+/// Generates a dungeon of rooms joined by corridors. The same seed and
+/// settings always produce the same dungeon, so printing the seed of a
+/// dungeon is enough to get back to it later.
+///
+/// The map has a `player_start` object in the first room, a `portal`
+/// out of the dungeon on the stairs tile in the last room, and a `spawn` object for
+/// every enemy in the rooms in between. The tiles are picked from the
+/// `wall`, `floor` and `stairs` terrains in the autotile rules.
+pub fn generate(seed: u64, settings: &DungeonSettings, autotile_info: &AutotileInfo) -> TileMap {
+    let mut rng = Rng::new(seed);
+    let (width, height) = (settings.dimensions.0 as i32, settings.dimensions.1 as i32);
+    let (min_size, max_size) = settings.room_size;
+
+    let mut terrain = vec![vec![WALL; width as usize]; height as usize];
+    let mut rooms: Vec<Room> = vec![];
+
+    for _ in 0..settings.room_attempts {
+        let room_width = rng.range(min_size, max_size + 1);
+        let room_height = rng.range(min_size, max_size + 1);
+
+        // Keep a border of wall around the outside of the map
+        let room = Room {
+            x: rng.range(1, width - room_width - 1),
+            y: rng.range(1, height - room_height - 1),
+            width: room_width,
+            height: room_height,
+        };
+
+        if rooms.iter().any(|other| room.too_close(other)) {
+            continue;
+        }
+
+        carve(&mut terrain, room.x, room.y, room.width, room.height);
+        if let Some(previous) = rooms.last() {
+            carve_corridor(&mut terrain, previous.center(), room.center());
+        }
+
+        rooms.push(room);
+    }
+
+    let mut objects = vec![];
+
+    if let Some(first) = rooms.first() {
+        let (x, y) = first.center();
+        objects.push(object(
+            "player_start",
+            "player_start",
+            x,
+            y,
+            settings.tile_dimensions,
+        ));
+    }
+
+    if let Some(last) = rooms.last() {
+        let (x, y) = last.center();
+        terrain[y as usize][x as usize] = STAIRS;
+        let mut stairs = object("stairs", "portal", x, y, settings.tile_dimensions);
+        stairs.properties.insert(
+            String::from("map"),
+            tiled::PropertyValue::StringValue(settings.exit_map.clone()),
+        );
+        stairs.properties.insert(
+            String::from("entrance"),
+            tiled::PropertyValue::StringValue(settings.exit_entrance.clone()),
+        );
+        objects.push(stairs);
+    }
+
+    if rooms.len() > 2 && !settings.enemy_prefabs.is_empty() {
+        for room in rooms[1..rooms.len() - 1].iter() {
+            let (fewest, most) = settings.enemies_per_room;
+
+            for _ in 0..rng.range(fewest, most + 1) {
+                // Stay off the edges so enemies don't spawn half in a wall
+                let x = rng.range(room.x + 1, room.x + room.width - 1);
+                let y = rng.range(room.y + 1, room.y + room.height - 1);
+                let prefab = &settings.enemy_prefabs
+                    [rng.range(0, settings.enemy_prefabs.len() as i32) as usize];
+
+                objects.push(object(prefab, "spawn", x, y, settings.tile_dimensions));
+            }
+        }
+    }
+
+    TileMap::new(
+        settings.dimensions,
+        settings.tile_dimensions,
+        1,
+        vec![autotile_info.apply(&terrain)],
+        autotile_info.solid_tiles(),
+        objects,
+    )
+}
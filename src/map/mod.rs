@@ -0,0 +1,5 @@
+pub mod autotile;
+pub mod dungeon;
+pub mod pathfinding;
+pub mod tilemap;
+pub use tilemap::*;
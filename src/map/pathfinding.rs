@@ -0,0 +1,70 @@
+use super::TileMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// The most tiles a single search will look at before giving up.
+/// This keeps a search for an unreachable tile from stalling a frame.
+const MAX_EXPLORED: usize = 2048;
+
+/// Finds a shortest path between two tiles using A*, moving only
+/// between orthogonally adjacent tiles that aren't solid. The path
+/// excludes `start` and ends with `goal`, so an empty path means
+/// we're already there. Returns `None` if the goal can't be reached.
+pub fn find_path(map: &TileMap, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+    if map.is_solid(goal.0, goal.1) {
+        return None;
+    }
+
+    let heuristic = |(x, y): (i32, i32)| (x - goal.0).abs() + (y - goal.1).abs();
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+    let mut cost: HashMap<(i32, i32), i32> = HashMap::new();
+
+    open.push(Reverse((heuristic(start), start)));
+    cost.insert(start, 0);
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut tile = current;
+            while let Some(previous) = came_from.get(&tile) {
+                if *previous == start {
+                    break;
+                }
+                path.push(*previous);
+                tile = *previous;
+            }
+
+            if start == goal {
+                path.clear();
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        if cost.len() > MAX_EXPLORED {
+            return None;
+        }
+
+        let (x, y) = current;
+        let next_cost = cost[&current] + 1;
+
+        for neighbour in [(x, y - 1), (x, y + 1), (x - 1, y), (x + 1, y)].iter() {
+            if map.is_solid(neighbour.0, neighbour.1) {
+                continue;
+            }
+
+            match cost.get(neighbour) {
+                Some(known_cost) if *known_cost <= next_cost => continue,
+                _ => {}
+            }
+
+            cost.insert(*neighbour, next_cost);
+            came_from.insert(*neighbour, current);
+            open.push(Reverse((next_cost + heuristic(*neighbour), *neighbour)));
+        }
+    }
+
+    None
+}
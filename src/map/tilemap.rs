@@ -0,0 +1,253 @@
+use std::collections::HashSet;
+
+/// An object placed on one of a map's object layers,
+/// such as a spawn point
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapObject {
+    pub name: String,
+    /// The object's type in tiled, which says what the object is for
+    pub kind: String,
+    /// The object's top left corner and size, in pixels
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub properties: tiled::Properties,
+}
+
+impl MapObject {
+    /// Returns the value of the string property with the given
+    /// name, if the object has one
+    pub fn string_property(&self, name: &str) -> Option<&str> {
+        match self.properties.get(name) {
+            Some(tiled::PropertyValue::StringValue(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the int property with the given
+    /// name, if the object has one
+    pub fn int_property(&self, name: &str) -> Option<i32> {
+        match self.properties.get(name) {
+            Some(tiled::PropertyValue::IntValue(value)) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// An in-memory tilemap. Tiled maps are loaded into
+/// this once, and everything that needs to know about the
+/// map (rendering, collision, ...) reads from it afterwards.
+pub struct TileMap {
+    /// The width and height of the map, in tiles
+    pub width: u32,
+    pub height: u32,
+    /// The width and height of each tile, in pixels
+    pub tile_dimensions: (u32, u32),
+    /// The gid of the first tile in the map's tileset. Subtracting
+    /// it from a gid gives the index of the tile in the sprite-sheet.
+    pub first_gid: u32,
+    /// Tile gids for each layer from the bottom up, indexed
+    /// as `layers[layer][y][x]`. A gid of 0 is an empty tile.
+    pub layers: Vec<Vec<Vec<u32>>>,
+    /// Whether each tile blocks movement, indexed as `collision[y][x]`
+    pub collision: Vec<Vec<bool>>,
+    /// The gids of tiles that block movement
+    pub solid_tiles: HashSet<u32>,
+    /// The gids of tiles that can be fished in
+    pub water_tiles: HashSet<u32>,
+    /// The objects from every object layer
+    pub objects: Vec<MapObject>,
+}
+
+impl TileMap {
+    /// Loads a map from a .tmx (tiled) file. Tiles are solid if the
+    /// tileset gives them a boolean `solid` property set to true, and
+    /// water if it gives them a boolean `water` property set to true.
+    pub fn from(file: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let tilemap = tiled::parse_file(std::path::Path::new(file))?;
+        let tileset = tilemap
+            .tilesets
+            .first()
+            .ok_or_else(|| format!("Map {} has no tileset", file))?;
+
+        let tiles_with = |property: &str| -> HashSet<u32> {
+            tileset
+                .tiles
+                .iter()
+                .filter(|tile| {
+                    matches!(
+                        tile.properties.get(property),
+                        Some(tiled::PropertyValue::BoolValue(true))
+                    )
+                })
+                .map(|tile| tile.id + tileset.first_gid)
+                .collect()
+        };
+        let solid_tiles = tiles_with("solid");
+        let water_tiles = tiles_with("water");
+
+        let mut layers = Vec::with_capacity(tilemap.layers.len());
+        for layer in tilemap.layers.iter() {
+            // We should really only ever have maps with finite layer-data
+            // See [tiled::LayerData](https://docs.rs/tiled/latest/tiled/enum.LayerData.html)
+            // for more info on what this is
+            let tiles = match &layer.tiles {
+                tiled::LayerData::Finite(tiles) => tiles,
+                _ => return Err(format!("Layer {} in {} is infinite", layer.name, file).into()),
+            };
+
+            layers.push(
+                tiles
+                    .iter()
+                    .map(|row| row.iter().map(|tile| tile.gid).collect())
+                    .collect(),
+            );
+        }
+
+        let objects = tilemap
+            .object_groups
+            .iter()
+            .flat_map(|group| group.objects.iter())
+            .map(|object| MapObject {
+                name: object.name.clone(),
+                kind: object.obj_type.clone(),
+                x: object.x as i32,
+                y: object.y as i32,
+                width: object.width as u32,
+                height: object.height as u32,
+                properties: object.properties.clone(),
+            })
+            .collect();
+
+        Ok(Self {
+            water_tiles,
+            ..Self::new(
+                (tilemap.width, tilemap.height),
+                (tilemap.tile_width, tilemap.tile_height),
+                tileset.first_gid,
+                layers,
+                solid_tiles,
+                objects,
+            )
+        })
+    }
+
+    /// Produces a map from its parts, working out the collision grid
+    /// from which tiles are solid. This is how maps that don't come from
+    /// a tiled file, like generated dungeons, should be made.
+    pub fn new(
+        dimensions: (u32, u32),
+        tile_dimensions: (u32, u32),
+        first_gid: u32,
+        layers: Vec<Vec<Vec<u32>>>,
+        solid_tiles: HashSet<u32>,
+        objects: Vec<MapObject>,
+    ) -> Self {
+        let (width, height) = dimensions;
+        let mut map = Self {
+            width,
+            height,
+            tile_dimensions,
+            first_gid,
+            layers,
+            collision: vec![vec![false; width as usize]; height as usize],
+            solid_tiles,
+            water_tiles: HashSet::new(),
+            objects,
+        };
+        map.rebuild_collision();
+
+        map
+    }
+
+    /// Recomputes the whole collision grid from the tiles on every layer
+    pub fn rebuild_collision(&mut self) {
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                self.collision[y][x] = self.tile_blocks(x, y);
+            }
+        }
+    }
+
+    fn tile_blocks(&self, x: usize, y: usize) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| self.solid_tiles.contains(&layer[y][x]))
+    }
+
+    /// The gid of the tile at (x, y) on the given layer,
+    /// or `None` if that's off the edge of the map
+    pub fn tile(&self, layer: usize, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        self.layers
+            .get(layer)?
+            .get(y as usize)?
+            .get(x as usize)
+            .copied()
+    }
+
+    /// Replaces the tile at (x, y) on the given layer, keeping the
+    /// collision grid up to date. Does nothing off the edge of the map.
+    pub fn set_tile(&mut self, layer: usize, x: i32, y: i32, gid: u32) {
+        if self.tile(layer, x, y).is_none() {
+            return;
+        }
+
+        let (x, y) = (x as usize, y as usize);
+        self.layers[layer][y][x] = gid;
+        self.collision[y][x] = self.tile_blocks(x, y);
+    }
+
+    /// Whether the tile at (x, y) blocks movement.
+    /// Everything off the edge of the map does.
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return true;
+        }
+
+        self.collision[y as usize][x as usize]
+    }
+
+    /// Whether there's water to fish in at (x, y) on any layer
+    pub fn is_water(&self, x: i32, y: i32) -> bool {
+        (0..self.layers.len()).any(|layer| {
+            self.tile(layer, x, y)
+                .is_some_and(|gid| self.water_tiles.contains(&gid))
+        })
+    }
+
+    /// Whether the tile under the pixel at (x, y) blocks movement
+    pub fn blocks_point(&self, x: i32, y: i32) -> bool {
+        let (tile_x, tile_y) = self.tile_at(x, y);
+        self.is_solid(tile_x, tile_y)
+    }
+
+    /// The coordinates of the tile under the pixel at (x, y)
+    pub fn tile_at(&self, x: i32, y: i32) -> (i32, i32) {
+        let (tile_width, tile_height) = self.tile_dimensions;
+        (
+            x.div_euclid(tile_width as i32),
+            y.div_euclid(tile_height as i32),
+        )
+    }
+
+    /// The pixel at the centre of the tile at (x, y)
+    pub fn tile_center(&self, x: i32, y: i32) -> (i32, i32) {
+        let (tile_width, tile_height) = self.tile_dimensions;
+        (
+            x * tile_width as i32 + tile_width as i32 / 2,
+            y * tile_height as i32 + tile_height as i32 / 2,
+        )
+    }
+
+    /// All the objects of the given kind
+    pub fn objects_of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a MapObject> {
+        self.objects
+            .iter()
+            .filter(move |object| object.kind == kind)
+    }
+}
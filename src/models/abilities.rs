@@ -0,0 +1,165 @@
+use super::components::*;
+use super::projectile;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{AbilityEffect, AbilityInfo, KitInfo};
+use legion::systems::CommandBuffer;
+use legion::*;
+
+/// How many frames it takes to regenerate one point of mana
+pub const MANA_REGEN_FRAMES: u32 = 20;
+
+/// Carries out the effect of an ability used by `caster`,
+/// returning how long the caster is locked in place for.
+fn perform(
+    ability: &AbilityInfo,
+    caster: Entity,
+    position: &Position,
+    hurtbox: &Hurtbox,
+    abilities: &mut Abilities,
+    commands: &mut CommandBuffer,
+) -> u32 {
+    let center = hurtbox.at(position).center();
+
+    match &ability.effect {
+        AbilityEffect::Projectile(info) => {
+            let (x, y) = position.direction.as_vector();
+            projectile::spawn(
+                commands,
+                caster,
+                (center.x(), center.y()),
+                (x as f32, y as f32),
+                info,
+            );
+
+            ability.cast_frames
+        }
+        AbilityEffect::Nova {
+            radius,
+            damage,
+            status,
+        } => {
+            commands.push((Hitbox {
+                owner: caster,
+                origin: (center.x(), center.y()),
+                direction: position.direction,
+                reach: *radius,
+                arc: 360.0,
+                damage: *damage,
+                status: *status,
+                frames_left: 1,
+                struck: vec![],
+            },));
+
+            ability.cast_frames
+        }
+        AbilityEffect::Combo { strikes, window } => {
+            let strike = strikes[abilities.combo_step % strikes.len()];
+
+            commands.push((Hitbox {
+                owner: caster,
+                origin: (center.x(), center.y()),
+                direction: position.direction,
+                reach: strike.reach,
+                arc: strike.arc,
+                damage: strike.damage,
+                status: None,
+                frames_left: 1,
+                struck: vec![],
+            },));
+
+            abilities.combo_step += 1;
+            if abilities.combo_step >= strikes.len() {
+                abilities.combo_step = 0;
+                abilities.combo_window = 0;
+            } else {
+                abilities.combo_window = strike.duration + window;
+            }
+
+            strike.duration
+        }
+    }
+}
+
+/// Legion systems for using class abilities
+pub mod systems {
+    use super::*;
+
+    /// Ticks ability cooldowns and mana regeneration, then uses the
+    /// queued ability if it's off cooldown and the caster has the mana.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn use_abilities(
+        entity: &Entity,
+        class: &PlayerClass,
+        position: &Position,
+        hurtbox: &Hurtbox,
+        stats: &mut PlayerStats,
+        state: &mut PlayerState,
+        timer: &mut StateTimer,
+        abilities: &mut Abilities,
+        #[resource] kit_info: &KitInfo,
+        commands: &mut CommandBuffer,
+    ) {
+        for cooldown in abilities.cooldowns.iter_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+
+        if abilities.combo_window > 0 {
+            abilities.combo_window -= 1;
+            if abilities.combo_window == 0 {
+                abilities.combo_step = 0;
+            }
+        }
+
+        if *state != PlayerState::Death {
+            if abilities.mana_regen == 0 {
+                stats.mana.restore(1);
+                abilities.mana_regen = MANA_REGEN_FRAMES;
+            }
+            abilities.mana_regen -= 1;
+        }
+
+        let slot = match abilities.queued.take() {
+            Some(slot) => slot,
+            None => return,
+        };
+
+        let ability = match kit_info.kit(*class).get(slot) {
+            Some(ability) => ability,
+            None => return,
+        };
+
+        if timer.0 > 0 || abilities.cooldowns[slot] > 0 || stats.mana.current < ability.mana_cost {
+            return;
+        }
+
+        stats.mana.deplete(ability.mana_cost);
+        abilities.cooldowns[slot] = ability.cooldown;
+
+        *state = PlayerState::Attack;
+        timer.0 = perform(ability, *entity, position, hurtbox, abilities, commands);
+    }
+
+    #[system(for_each)]
+    pub fn draw_hotbar(
+        _: &IsPlayerCharacter,
+        class: &PlayerClass,
+        abilities: &Abilities,
+        #[resource] kit_info: &KitInfo,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        let mut slots = [None; HOTBAR_SLOTS];
+
+        for (slot, ability) in kit_info.kit(*class).iter().take(HOTBAR_SLOTS).enumerate() {
+            let cooldown = if ability.cooldown == 0 {
+                0.0
+            } else {
+                abilities.cooldowns[slot] as f32 / ability.cooldown as f32
+            };
+
+            slots[slot] = Some((ability.icon, cooldown));
+        }
+
+        render_queue.push(RenderRequest::Hotbar(slots));
+    }
+}
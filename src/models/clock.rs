@@ -0,0 +1,146 @@
+use super::zone::{Zone, ZoneMap};
+use crate::rendering::{RenderQueue, RenderRequest};
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+/// How many frames an in-game hour lasts for
+pub const HOUR_FRAMES: u64 = 1000;
+/// How many frames an in-game day lasts for
+pub const DAY_FRAMES: u64 = HOUR_FRAMES * 24;
+/// The hour a new game starts at
+pub const START_HOUR: u64 = 8;
+
+/// The colour the world is tinted at each hour, from midnight. The
+/// tint between hours is blended from the hours either side.
+const DAYLIGHT: [(u8, u8, u8); 24] = [
+    (60, 70, 130),
+    (60, 70, 130),
+    (60, 70, 130),
+    (60, 70, 130),
+    (70, 80, 140),
+    (120, 110, 160),
+    (200, 170, 170),
+    (240, 220, 210),
+    (255, 250, 240),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 255, 255),
+    (255, 240, 220),
+    (240, 190, 160),
+    (200, 140, 140),
+    (130, 100, 150),
+    (90, 85, 145),
+    (70, 75, 135),
+    (60, 70, 130),
+];
+
+/// The broad parts of the day that things like fishing care about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum TimeOfDay {
+    /// From 5 until 9
+    Morning,
+    /// From 9 until 17
+    Day,
+    /// From 17 until 21
+    Evening,
+    /// From 21 until 5
+    Night,
+}
+
+/// The in-game time, counted in frames since the first day began
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WorldClock {
+    pub frames: u64,
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        WorldClock {
+            frames: START_HOUR * HOUR_FRAMES,
+        }
+    }
+}
+
+impl WorldClock {
+    /// How many whole days have gone by, starting from 0
+    pub fn day(&self) -> u64 {
+        self.frames / DAY_FRAMES
+    }
+
+    /// The hour of the day, from 0 to 23
+    pub fn hour(&self) -> u32 {
+        (self.frames % DAY_FRAMES / HOUR_FRAMES) as u32
+    }
+
+    /// The minute of the hour, from 0 to 59
+    pub fn minute(&self) -> u32 {
+        (self.frames % HOUR_FRAMES * 60 / HOUR_FRAMES) as u32
+    }
+
+    /// The colour to tint the world at this time of day, where white leaves it as it is
+    pub fn daylight(&self) -> (u8, u8, u8) {
+        let hour = self.hour() as usize;
+        let (from, to) = (DAYLIGHT[hour], DAYLIGHT[(hour + 1) % DAYLIGHT.len()]);
+        let blend = |from: u8, to: u8| {
+            (from as i32 + (to as i32 - from as i32) * self.minute() as i32 / 60) as u8
+        };
+
+        (
+            blend(from.0, to.0),
+            blend(from.1, to.1),
+            blend(from.2, to.2),
+        )
+    }
+
+    pub fn time_of_day(&self) -> TimeOfDay {
+        match self.hour() {
+            5..=8 => TimeOfDay::Morning,
+            9..=16 => TimeOfDay::Day,
+            17..=20 => TimeOfDay::Evening,
+            _ => TimeOfDay::Night,
+        }
+    }
+}
+
+/// Legion systems for keeping time
+pub mod systems {
+    use super::*;
+
+    #[system]
+    pub fn tick_clock(#[resource] clock: &mut WorldClock) {
+        clock.frames += 1;
+    }
+
+    /// Tints the world for the time of day. Dungeons are
+    /// underground, so they look the same at any hour.
+    #[system]
+    pub fn draw_daylight(
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] clock: &WorldClock,
+        #[resource] zone: &Zone,
+    ) {
+        if let ZoneMap::Dungeon(_) = zone.map {
+            return;
+        }
+
+        let tint = clock.daylight();
+        if tint != (255, 255, 255) {
+            render_queue.push(RenderRequest::Tint(tint));
+        }
+    }
+
+    /// Puts the day and time on the HUD
+    #[system]
+    pub fn draw_clock(#[resource] render_queue: &mut RenderQueue, #[resource] clock: &WorldClock) {
+        render_queue.push(RenderRequest::Clock {
+            day: clock.day() + 1,
+            hour: clock.hour(),
+            minute: clock.minute(),
+        });
+    }
+}
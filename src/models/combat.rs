@@ -0,0 +1,343 @@
+use super::components::*;
+use crate::util::AttackInfo;
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use sdl2::rect::Rect;
+use std::collections::HashMap;
+
+/// How long an entity flinches for after being hit
+pub const FLINCH_FRAMES: u32 = 6;
+/// How long an entity ignores further damage after being hit
+pub const INVULNERABILITY_FRAMES: u32 = 20;
+/// How long an entity lies dead before respawning
+pub const RESPAWN_FRAMES: u32 = 60;
+
+/// Puts an entity back at its spawn point with full health and mana
+fn respawn(
+    stats: &mut PlayerStats,
+    position: &mut Position,
+    state: &mut PlayerState,
+    invulnerability: &mut Invulnerability,
+    spawn: &SpawnPoint,
+) {
+    stats.health.refill();
+    stats.mana.refill();
+
+    position.x = spawn.x;
+    position.y = spawn.y;
+    position.direction = Direction::Down;
+
+    *state = PlayerState::Idle;
+    // Give the player a moment to get their bearings
+    invulnerability.0 = INVULNERABILITY_FRAMES;
+}
+
+/// Whether any part of `rect` lies inside the wedge of radius `reach`
+/// and angular width `arc` (in degrees) that opens out from `origin`
+/// towards `direction`.
+pub fn wedge_overlaps(
+    origin: (i32, i32),
+    direction: Direction,
+    reach: u32,
+    arc: f32,
+    rect: Rect,
+) -> bool {
+    let (origin_x, origin_y) = (origin.0 as f32, origin.1 as f32);
+    let (facing_x, facing_y) = direction.as_vector();
+    let min_cos = (arc / 2.0).to_radians().cos();
+
+    let inside = |x: f32, y: f32| {
+        let (dx, dy) = (x - origin_x, y - origin_y);
+        let distance = (dx * dx + dy * dy).sqrt();
+
+        if distance > reach as f32 {
+            return false;
+        }
+        if distance == 0.0 {
+            return true;
+        }
+
+        (dx * facing_x as f32 + dy * facing_y as f32) / distance >= min_cos
+    };
+
+    // Checking the point of the rectangle closest to the origin catches
+    // targets at the edge of our reach, and checking the centre catches
+    // targets that we're standing right up against.
+    let closest_x = origin_x.clamp(rect.left() as f32, rect.right() as f32);
+    let closest_y = origin_y.clamp(rect.top() as f32, rect.bottom() as f32);
+    let center = rect.center();
+
+    inside(closest_x, closest_y) || inside(center.x() as f32, center.y() as f32)
+}
+
+/// Collects the faction of every entity that has one
+pub fn factions(world: &SubWorld) -> HashMap<Entity, Faction> {
+    <(Entity, &Faction)>::query()
+        .iter(world)
+        .map(|(entity, faction)| (*entity, *faction))
+        .collect()
+}
+
+/// Whether an attack by `attacker` is allowed to land on `target`.
+/// Nothing can hurt itself or its own faction, but entities without
+/// a faction can hurt and be hurt by anything else.
+pub fn can_hit(factions: &HashMap<Entity, Faction>, attacker: Entity, target: Entity) -> bool {
+    if attacker == target {
+        return false;
+    }
+
+    match (factions.get(&attacker), factions.get(&target)) {
+        (Some(attacker), Some(target)) => attacker != target,
+        _ => true,
+    }
+}
+
+/// Legion systems that deal with attacks, damage, death and respawning
+pub mod systems {
+    use super::*;
+
+    /// Drives an entity's melee attack once its state is set to
+    /// [PlayerState::Attack]: the attack locks the entity's state for its
+    /// duration and spawns a [Hitbox] when it reaches its active frames.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn melee(
+        entity: &Entity,
+        class: &PlayerClass,
+        position: &Position,
+        hurtbox: &Hurtbox,
+        state: &mut PlayerState,
+        timer: &mut StateTimer,
+        melee: &mut MeleeState,
+        #[resource] attack_info: &AttackInfo,
+        commands: &mut CommandBuffer,
+    ) {
+        if *state != PlayerState::Attack {
+            melee.frame = 0;
+            melee.cooldown = melee.cooldown.saturating_sub(1);
+            return;
+        }
+
+        let attack = attack_info.get(*class);
+
+        if melee.frame == 0 {
+            // Abilities play the attack animation too,
+            // in which case the state is already locked
+            if timer.0 > 0 {
+                return;
+            }
+
+            if melee.cooldown > 0 {
+                *state = PlayerState::Idle;
+                return;
+            }
+
+            timer.0 = attack.duration;
+            melee.cooldown = attack.cooldown;
+        }
+
+        melee.frame += 1;
+
+        if melee.frame == attack.active_frames.0 {
+            let center = hurtbox.at(position).center();
+            let (start, end) = attack.active_frames;
+
+            commands.push((Hitbox {
+                owner: *entity,
+                origin: (center.x(), center.y()),
+                direction: position.direction,
+                reach: attack.reach,
+                arc: attack.arc,
+                damage: attack.damage,
+                status: None,
+                frames_left: end - start + 1,
+                struck: vec![],
+            },));
+        }
+    }
+
+    /// Tests every live hitbox against every hurtbox, queueing damage and
+    /// status effects for each new overlap, and removes hitboxes whose
+    /// active frames are over.
+    #[system]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[read_component(Faction)]
+    #[write_component(Hitbox)]
+    pub fn resolve_hits(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] damage_queue: &mut DamageQueue,
+        #[resource] status_queue: &mut StatusQueue,
+    ) {
+        let targets: Vec<(Entity, Rect)> = <(Entity, &Position, &Hurtbox)>::query()
+            .iter(world)
+            .map(|(entity, position, hurtbox)| (*entity, hurtbox.at(position)))
+            .collect();
+        let factions = factions(world);
+
+        for (entity, hitbox) in <(Entity, &mut Hitbox)>::query().iter_mut(world) {
+            for (target, rect) in targets.iter() {
+                if !can_hit(&factions, hitbox.owner, *target) || hitbox.struck.contains(target) {
+                    continue;
+                }
+
+                if wedge_overlaps(
+                    hitbox.origin,
+                    hitbox.direction,
+                    hitbox.reach,
+                    hitbox.arc,
+                    *rect,
+                ) {
+                    hitbox.struck.push(*target);
+                    damage_queue.push(DamageEvent {
+                        target: *target,
+                        source: Some(hitbox.owner),
+                        amount: hitbox.damage,
+                        over_time: false,
+                    });
+                    if let Some(status) = hitbox.status {
+                        status_queue.push(StatusEvent {
+                            target: *target,
+                            source: Some(hitbox.owner),
+                            status,
+                        });
+                    }
+                }
+            }
+
+            hitbox.frames_left = hitbox.frames_left.saturating_sub(1);
+            if hitbox.frames_left == 0 {
+                commands.remove(*entity);
+            }
+        }
+    }
+
+    /// Drains the damage queue, applying each event to its target's health
+    /// plus the attack of its source and less the target's defence, and
+    /// raising a [DeathEvent] for each target that's killed. Targets that
+    /// are invulnerable or already dead are left untouched.
+    #[system]
+    #[read_component(EffectiveStats)]
+    #[write_component(PlayerStats)]
+    #[write_component(EnemyStats)]
+    #[write_component(PlayerState)]
+    #[write_component(StateTimer)]
+    #[write_component(Invulnerability)]
+    pub fn apply_damage(
+        world: &mut SubWorld,
+        #[resource] damage_queue: &mut DamageQueue,
+        #[resource] death_queue: &mut DeathQueue,
+    ) {
+        // Deaths only stay in the queue for the frame they happen in
+        death_queue.clear();
+
+        for event in damage_queue.drain(..) {
+            // Attack strengthens every hit, whether it's from a weapon, an ability
+            // or a projectile, but not damage over time
+            let attack = event
+                .source
+                .filter(|_| !event.over_time)
+                .and_then(|source| world.entry_ref(source).ok())
+                .and_then(|entry| {
+                    entry
+                        .get_component::<EffectiveStats>()
+                        .ok()
+                        .map(|effective| effective.attack)
+                })
+                .unwrap_or(0);
+
+            let mut entry = match world.entry_mut(event.target) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            let invulnerable = entry
+                .get_component::<Invulnerability>()
+                .is_ok_and(|invulnerability| invulnerability.0 > 0);
+            let dead = entry
+                .get_component::<PlayerState>()
+                .is_ok_and(|state| *state == PlayerState::Death);
+
+            if (invulnerable && !event.over_time) || dead {
+                continue;
+            }
+
+            // Defence softens every hit, but never to nothing
+            let amount = event.amount + attack;
+            let amount = match entry.get_component::<EffectiveStats>() {
+                Ok(effective) => amount.saturating_sub(effective.defence).max(1),
+                Err(_) => amount,
+            };
+
+            let killed = if let Ok(stats) = entry.get_component_mut::<PlayerStats>() {
+                stats.health.deplete(amount);
+                stats.health.is_empty()
+            } else if let Ok(stats) = entry.get_component_mut::<EnemyStats>() {
+                stats.health.deplete(amount);
+                stats.health.is_empty()
+            } else {
+                continue;
+            };
+
+            let (state, frames) = if killed {
+                death_queue.push(DeathEvent {
+                    entity: event.target,
+                    killer: event.source,
+                });
+                (PlayerState::Death, RESPAWN_FRAMES)
+            } else if event.over_time {
+                continue;
+            } else {
+                (PlayerState::TakingDamage, FLINCH_FRAMES)
+            };
+
+            if let Ok(current_state) = entry.get_component_mut::<PlayerState>() {
+                *current_state = state;
+            }
+            if let Ok(timer) = entry.get_component_mut::<StateTimer>() {
+                timer.0 = frames;
+            }
+            if let Ok(invulnerability) = entry.get_component_mut::<Invulnerability>() {
+                invulnerability.0 = INVULNERABILITY_FRAMES;
+            }
+        }
+    }
+
+    #[system(for_each)]
+    pub fn tick_invulnerability(invulnerability: &mut Invulnerability) {
+        invulnerability.0 = invulnerability.0.saturating_sub(1);
+    }
+
+    /// Counts down attacks, flinching and death, returning the entity to idle
+    /// (and its spawn point, if it died) once the timer runs out.
+    #[system(for_each)]
+    pub fn recover(
+        timer: &mut StateTimer,
+        state: &mut PlayerState,
+        stats: &mut PlayerStats,
+        position: &mut Position,
+        invulnerability: &mut Invulnerability,
+        spawn: &SpawnPoint,
+    ) {
+        if timer.0 == 0 {
+            return;
+        }
+
+        timer.0 -= 1;
+        if timer.0 > 0 {
+            return;
+        }
+
+        match *state {
+            PlayerState::Death => {
+                respawn(stats, position, state, invulnerability, spawn);
+            }
+            PlayerState::TakingDamage | PlayerState::Attack => {
+                *state = PlayerState::Idle;
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,4 +1,9 @@
-#[derive(Clone, Copy, Debug, PartialEq)]
+use crate::util::{ExperienceCurve, ItemCategory, ProjectileInfo};
+use legion::Entity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -6,7 +11,7 @@ pub struct Position {
     pub direction: Direction,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Direction {
     Up = 0,
     Down = 1,
@@ -14,7 +19,20 @@ pub enum Direction {
     Right = 3,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+impl Direction {
+    /// The unit vector pointing in this direction,
+    /// in screen coordinates (y grows downwards)
+    pub fn as_vector(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum Gender {
     Cringe = 0,
     Based = 1,
@@ -29,7 +47,7 @@ impl ToString for Gender {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum PlayerClass {
     Warrior,
     Mage,
@@ -68,7 +86,7 @@ impl ToString for PlayerClass {
 //     pub max: u32,
 // }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct PrimaryStat {
     pub current: u32,
     pub max: u32,
@@ -78,9 +96,28 @@ impl PrimaryStat {
     pub fn as_percent(&self) -> f32 {
         self.current as f32 / self.max as f32
     }
+
+    /// Lowers the current value by `amount`, stopping at zero
+    pub fn deplete(&mut self, amount: u32) {
+        self.current = self.current.saturating_sub(amount);
+    }
+
+    /// Raises the current value by `amount`, stopping at the max
+    pub fn restore(&mut self, amount: u32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Sets the current value back to the max
+    pub fn refill(&mut self) {
+        self.current = self.max;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current == 0
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct PlayerStats {
     pub health: PrimaryStat,
     pub mana: PrimaryStat,
@@ -108,7 +145,7 @@ impl Default for PlayerStats {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum PlayerState {
     Moving = 0,
     Idle = 1,
@@ -117,17 +154,867 @@ pub enum PlayerState {
     TakingDamage = 4,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Id(pub u32);
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Input {
     Move(Direction),
     Attack,
+    /// Use the ability in the given hotbar slot
+    Ability(usize),
     Run,
     Quit,
+    /// Pick up the nearest item on the ground
+    PickUp,
+    ToggleInventory,
+    /// Move around a menu, like the inventory screen
+    Navigate(Direction),
+    UseItem,
+    DropItem,
+    /// Talk to the nearest NPC, fish or tend a farm in front
+    /// of the player, or move a conversation along
+    Interact,
+    /// Leave a menu, like a shop
+    Back,
+    ToggleSkills,
+    /// Open the crafting menu for recipes that don't need a station
+    ToggleCrafting,
+    /// Open the menu for saving to a slot
+    SaveGame,
+    /// Open the menu for loading a save
+    LoadGame,
 }
 
 pub type InputQueue = Vec<Input>;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct IsPlayerCharacter;
+
+/// Marks an entity that outlives the zone it's in. Everything
+/// without this is removed when the player moves to another zone.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Global;
+
+/// A request to remove `amount` health from the `target` entity.
+/// `source` is the entity responsible for the damage, if any.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub amount: u32,
+    /// Whether this is a tick of damage over time, like burning. Damage over
+    /// time gets through invulnerability and doesn't make its target flinch.
+    pub over_time: bool,
+}
+
+pub type DamageQueue = Vec<DamageEvent>;
+
+/// Raised when an entity's health hits zero. `killer` is the
+/// source of the damage that finished it off, if any.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+/// The deaths that happened this frame
+pub type DeathQueue = Vec<DeathEvent>;
+
+/// A request to give `amount` experience to the `target` entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExperienceEvent {
+    pub target: Entity,
+    pub amount: u32,
+}
+
+pub type ExperienceQueue = Vec<ExperienceEvent>;
+
+/// The number of frames an entity will keep ignoring incoming damage
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Invulnerability(pub u32);
+
+/// The number of frames left before an entity's current state
+/// (flinching, lying dead, ...) ends and input is accepted again
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StateTimer(pub u32);
+
+/// Where an entity is placed when it respawns
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SpawnPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The region of an entity, relative to its [Position],
+/// that can be struck by attacks
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Hurtbox {
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Hurtbox {
+    /// The hurtbox as a rectangle in world coordinates
+    pub fn at(&self, position: &Position) -> sdl2::rect::Rect {
+        sdl2::rect::Rect::new(
+            position.x + self.x_offset,
+            position.y + self.y_offset,
+            self.width,
+            self.height,
+        )
+    }
+}
+
+/// A short-lived wedge in front of an attacker that damages
+/// every hurtbox it overlaps, at most once per target
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Hitbox {
+    pub owner: Entity,
+    pub origin: (i32, i32),
+    pub direction: Direction,
+    pub reach: u32,
+    pub arc: f32,
+    pub damage: u32,
+    /// The status effect inflicted on everything struck
+    pub status: Option<Status>,
+    pub frames_left: u32,
+    pub struck: Vec<Entity>,
+}
+
+/// Tracks how far into its current melee attack an entity
+/// is, and how long until it may attack again
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MeleeState {
+    pub frame: u32,
+    pub cooldown: u32,
+}
+
+/// The number of ability slots on the hotbar
+pub const HOTBAR_SLOTS: usize = 4;
+
+/// Tracks an entity's use of its class abilities
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Abilities {
+    /// Frames until the ability in each slot can be used again
+    pub cooldowns: [u32; HOTBAR_SLOTS],
+    /// The slot of an ability waiting to be used this frame
+    pub queued: Option<usize>,
+    /// The next strike of the current combo
+    pub combo_step: usize,
+    /// Frames left to continue the current combo
+    pub combo_window: u32,
+    /// Frames until the next point of mana is regenerated
+    pub mana_regen: u32,
+}
+
+impl Default for Abilities {
+    fn default() -> Self {
+        Abilities {
+            cooldowns: [0; HOTBAR_SLOTS],
+            queued: None,
+            combo_step: 0,
+            combo_window: 0,
+            mana_regen: 0,
+        }
+    }
+}
+
+/// How far an entity moves each frame, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Velocity {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A moving attack that damages the hurtboxes it flies into
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Projectile {
+    pub owner: Entity,
+    pub info: ProjectileInfo,
+    pub frames_left: u32,
+    /// How many more targets the projectile can pass through
+    pub pierce: u32,
+    pub struck: Vec<Entity>,
+}
+
+/// The experience awarded to whoever kills this entity
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExperienceReward(pub u32);
+
+/// The number of frames left to show an entity's level-up notification
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LevelUpNotice(pub u32);
+
+/// Which side an entity fights on. Attacks never land on
+/// entities of the same faction as the attacker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Faction {
+    Players,
+    Monsters,
+}
+
+/// The name of the prefab an entity was spawned from
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PrefabName(pub String);
+
+/// The name of the directional sprites an entity is drawn with
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct DirectionalSprite(pub String);
+
+/// A single sprite from a sheet that an entity is drawn with
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StaticSprite {
+    pub sheet: String,
+    pub index: usize,
+}
+
+/// Marks an entity as a non-player character
+/// that players can talk to
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Npc {
+    /// The name of the conversation the NPC starts
+    pub dialogue: Option<String>,
+}
+
+/// An NPC that walks between places over the course of the day
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct NpcSchedule {
+    /// The name of the schedule the NPC keeps to
+    pub schedule: String,
+    /// The place the NPC is at or on its way to
+    pub place: Option<String>,
+    /// The tiles left to walk through to get there
+    pub path: Vec<(i32, i32)>,
+}
+
+/// Items lying on the ground, waiting to be picked up
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct GroundItem {
+    pub item: String,
+    pub count: u32,
+}
+
+/// The name of the loot table rolled when this entity dies, or when it's opened
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LootTable(pub String);
+
+/// A chest that players can open for the loot inside
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Chest {
+    pub opened: bool,
+}
+
+/// How many more frames an entity sticks around for before it's removed
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Despawn(pub u32);
+
+/// How much money an entity has
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Gold(pub u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct EnemyStats {
+    pub health: PrimaryStat,
+    pub damage: u32,
+    /// How far the enemy moves each frame, in pixels
+    pub speed: i32,
+    /// How close the enemy needs to be to its target to attack, in pixels
+    pub attack_range: u32,
+    /// Frames between attacks
+    pub attack_cooldown: u32,
+    /// Set for enemies that attack from range rather than in melee
+    pub projectile: Option<ProjectileInfo>,
+}
+
+/// Distances, in pixels, that shape how an enemy behaves
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AiProfile {
+    /// How close a player needs to get before the enemy gives chase
+    pub aggro_radius: u32,
+    /// How far from its spawn point the enemy will chase before giving up
+    pub leash_radius: u32,
+    /// How far from its spawn point the enemy wanders while idle
+    pub wander_radius: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum AiState {
+    /// Standing around until it's time to wander
+    Idle,
+    /// Walking to a random spot near the spawn point
+    Wander,
+    /// Closing the distance to a target
+    Chase(Entity),
+    /// In range of a target and attacking whenever off cooldown
+    Attack(Entity),
+    /// Giving up and heading back to the spawn point
+    Leash,
+}
+
+/// The state of an enemy's behaviour
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Ai {
+    pub profile: AiProfile,
+    pub state: AiState,
+    /// The tiles left to walk through to reach the current destination
+    pub path: Vec<(i32, i32)>,
+    /// Frames left idling, or until the path to a target is recomputed
+    pub timer: u32,
+    /// Frames until the enemy can attack again
+    pub cooldown: u32,
+}
+
+impl Ai {
+    pub fn new(profile: AiProfile) -> Self {
+        Ai {
+            profile,
+            state: AiState::Idle,
+            path: vec![],
+            timer: 0,
+            cooldown: 0,
+        }
+    }
+}
+
+/// How many slots an inventory has, and how many
+/// of them fit on each row of the inventory screen
+pub const INVENTORY_SLOTS: usize = 24;
+pub const INVENTORY_COLUMNS: usize = 6;
+
+/// Some number of one item
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+/// The items an entity is carrying, and the state of its inventory screen
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+    /// The slot highlighted on the inventory screen
+    pub selected: usize,
+    pub open: bool,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory {
+            slots: vec![None; INVENTORY_SLOTS],
+            selected: 0,
+            open: false,
+        }
+    }
+}
+
+impl Inventory {
+    /// Adds `count` of `item`, topping up stacks of it that aren't full
+    /// before starting new ones in empty slots. Returns how many didn't fit.
+    pub fn add(&mut self, item: &str, count: u32, stack_size: u32) -> u32 {
+        let mut left = count;
+
+        for stack in self.slots.iter_mut().flatten() {
+            if left == 0 {
+                break;
+            }
+            if stack.item == item && stack.count < stack_size {
+                let added = left.min(stack_size - stack.count);
+                stack.count += added;
+                left -= added;
+            }
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if left == 0 {
+                break;
+            }
+            let added = left.min(stack_size);
+            *slot = Some(ItemStack {
+                item: String::from(item),
+                count: added,
+            });
+            left -= added;
+        }
+
+        left
+    }
+
+    /// Takes up to `count` items out of a slot, emptying it if none
+    /// are left. Returns what was taken, if there was anything there.
+    pub fn take(&mut self, slot: usize, count: u32) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(slot)?.as_mut()?;
+        let taken = count.min(stack.count);
+        let item = stack.item.clone();
+
+        stack.count -= taken;
+        if stack.count == 0 {
+            self.slots[slot] = None;
+        }
+
+        Some(ItemStack { item, count: taken })
+    }
+
+    /// How many of `item` there are across every slot
+    pub fn count(&self, item: &str) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Removes `count` of `item` from wherever they're kept. Nothing is
+    /// removed unless there are enough; returns whether they were.
+    pub fn remove(&mut self, item: &str, count: u32) -> bool {
+        if self.count(item) < count {
+            return false;
+        }
+
+        let mut left = count;
+        for slot in self.slots.iter_mut() {
+            if left == 0 {
+                break;
+            }
+            if let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) {
+                let taken = left.min(stack.count);
+                stack.count -= taken;
+                left -= taken;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// How far a player has got with a quest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum QuestStatus {
+    Active,
+    Completed,
+}
+
+/// A quest a player has started, and how far they've got with each of its objectives
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Quest {
+    pub name: String,
+    pub status: QuestStatus,
+    /// Progress towards each objective, in the order they're defined in
+    pub progress: Vec<u32>,
+    /// Whether the quest's rewards have been handed out
+    pub rewarded: bool,
+}
+
+/// The quests a player has started, in the order they started them.
+/// Quests that aren't in the log haven't been started yet.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct QuestLog {
+    pub quests: Vec<Quest>,
+}
+
+impl QuestLog {
+    pub fn status(&self, quest: &str) -> Option<QuestStatus> {
+        self.quests
+            .iter()
+            .find(|entry| entry.name == quest)
+            .map(|entry| entry.status)
+    }
+
+    /// Adds a quest to the log, unless it's already in there
+    pub fn start(&mut self, quest: &str) {
+        if self.status(quest).is_none() {
+            self.quests.push(Quest {
+                name: String::from(quest),
+                status: QuestStatus::Active,
+                progress: vec![],
+                rewarded: false,
+            });
+        }
+    }
+
+    /// Marks a quest as completed, starting it first if need be.
+    /// Its rewards are handed out once the quest systems see it.
+    pub fn complete(&mut self, quest: &str) {
+        self.start(quest);
+        if let Some(entry) = self.quests.iter_mut().find(|entry| entry.name == quest) {
+            entry.status = QuestStatus::Completed;
+        }
+    }
+}
+
+/// A player finishing a conversation with an NPC
+#[derive(Clone, Debug, PartialEq)]
+pub struct TalkEvent {
+    pub player: Entity,
+    /// The name of the dialogue tree the conversation followed
+    pub dialogue: String,
+}
+
+pub type TalkQueue = Vec<TalkEvent>;
+
+/// A life skill, levelled up separately from combat
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Skill {
+    Fishing,
+    Farming,
+    Cooking,
+    Crafting,
+    Mining,
+}
+
+/// Every skill, in the order they're shown on the skills page
+pub const SKILLS: [Skill; 5] = [
+    Skill::Fishing,
+    Skill::Farming,
+    Skill::Cooking,
+    Skill::Crafting,
+    Skill::Mining,
+];
+
+/// How far along a player is with a skill
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SkillLevel {
+    pub level: u32,
+    pub experience: PrimaryStat,
+}
+
+impl SkillLevel {
+    /// A skill that's never been practised
+    pub fn new(curve: &ExperienceCurve) -> Self {
+        SkillLevel {
+            level: 1,
+            experience: PrimaryStat {
+                current: 0,
+                max: curve.required(1).max(1),
+            },
+        }
+    }
+}
+
+/// A player's level in each life skill. Skills they've
+/// never practised are at level 1 with no experience.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Skills(pub HashMap<Skill, SkillLevel>);
+
+impl Skills {
+    pub fn level(&self, skill: Skill) -> u32 {
+        self.0.get(&skill).map_or(1, |skill| skill.level)
+    }
+
+    /// How far along a skill is, with the given curve for skills that have never been practised
+    pub fn get(&self, skill: Skill, curve: &ExperienceCurve) -> SkillLevel {
+        self.0
+            .get(&skill)
+            .copied()
+            .unwrap_or_else(|| SkillLevel::new(curve))
+    }
+
+    /// Adds experience to a skill, levelling it up as many times as it
+    /// takes to bring the experience back under the max. Returns whether
+    /// any levels were gained.
+    pub fn gain(&mut self, skill: Skill, amount: u32, curve: &ExperienceCurve) -> bool {
+        let skill = self
+            .0
+            .entry(skill)
+            .or_insert_with(|| SkillLevel::new(curve));
+        let mut levelled_up = false;
+
+        skill.experience.current += amount;
+        while skill.experience.current >= skill.experience.max {
+            skill.experience.current -= skill.experience.max;
+            skill.level += 1;
+            skill.experience.max = curve.required(skill.level).max(1);
+            levelled_up = true;
+        }
+
+        levelled_up
+    }
+}
+
+/// A request to give `amount` experience in `skill` to the `target` entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SkillExperienceEvent {
+    pub target: Entity,
+    pub skill: Skill,
+    pub amount: u32,
+}
+
+pub type SkillExperienceQueue = Vec<SkillExperienceEvent>;
+
+/// Marks a player who has the skills page open
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SkillsPage;
+
+/// Where a player is up to with their fishing
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum FishingPhase {
+    /// Swinging the rod out over the water
+    Casting,
+    /// Waiting for something to bite
+    Waiting,
+    /// Something's on the line. It's caught by stopping the `marker`,
+    /// which sweeps back and forth across the bar, between the edges
+    /// of the `target`.
+    Hooked {
+        item: String,
+        experience: u32,
+        marker: i32,
+        speed: i32,
+        target: (i32, i32),
+    },
+}
+
+/// A player with their line in the water
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Fishing {
+    /// The tile being fished, in tile coordinates
+    pub spot: (i32, i32),
+    pub phase: FishingPhase,
+    /// How long until the current phase runs out
+    pub frames_left: u32,
+}
+
+/// A crop growing on a tilled tile
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Crop {
+    /// The name of the crop's definition
+    pub name: String,
+    /// The tile it's planted on, in tile coordinates
+    pub tile: (i32, i32),
+    /// How many stages it's grown through
+    pub stage: usize,
+    /// How many watered days it's spent at its current stage
+    pub days: u32,
+}
+
+/// A shop a player is browsing
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Shopping {
+    /// The name of the shop
+    pub shop: String,
+    /// Whether the player is selling their own items rather than buying the shop's
+    pub selling: bool,
+    /// Which row of the list being browsed is highlighted
+    pub selected: usize,
+}
+
+impl Shopping {
+    pub fn new(shop: &str) -> Self {
+        Shopping {
+            shop: String::from(shop),
+            selling: false,
+            selected: 0,
+        }
+    }
+}
+
+/// A player with the crafting menu open
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Crafting {
+    /// The name of the station being used, if any. Without one
+    /// only recipes that don't need a station can be made.
+    pub station: Option<String>,
+    /// Which recipe in the list is highlighted
+    pub selected: usize,
+}
+
+/// Where a game is saved to: the autosave made on the way into each
+/// zone, or one of the slots players save to themselves, from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SaveSlot {
+    Autosave,
+    Manual(usize),
+}
+
+/// A request to save the game to, or load it from, a slot.
+/// These are carried out between frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SaveRequest {
+    Save(SaveSlot),
+    Load(SaveSlot),
+}
+
+pub type SaveQueue = Vec<SaveRequest>;
+
+/// A player with the save or load menu open
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SaveMenu {
+    /// Whether the menu loads from the slots rather than saving to them
+    pub loading: bool,
+    /// Each slot on offer, and a description of what's saved in it
+    /// if there's anything
+    pub slots: Vec<(SaveSlot, Option<String>)>,
+    /// Which slot is highlighted
+    pub selected: usize,
+}
+
+/// A conversation a player is having with an NPC
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Conversation {
+    pub npc: Entity,
+    /// The name of the dialogue tree being followed
+    pub dialogue: String,
+    /// The name of the line in the tree that's being said
+    pub node: String,
+    /// Whether the line's branches have been followed and its actions taken yet
+    pub entered: bool,
+    /// How many characters of the line have been revealed so far
+    pub shown: usize,
+    /// Which of the line's choices is highlighted
+    pub selected: usize,
+}
+
+impl Conversation {
+    pub fn new(npc: Entity, dialogue: &str, node: &str) -> Self {
+        Conversation {
+            npc,
+            dialogue: String::from(dialogue),
+            node: String::from(node),
+            entered: false,
+            shown: 0,
+            selected: 0,
+        }
+    }
+
+    /// Moves on to another line of the same dialogue tree
+    pub fn go_to(&mut self, node: &str) {
+        self.node = String::from(node);
+        self.entered = false;
+        self.shown = 0;
+        self.selected = 0;
+    }
+}
+
+/// Flat bonuses to a character's stats, from equipment or buffs.
+/// Bonuses can be negative, for things like curses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StatBonus {
+    pub attack: i32,
+    pub defence: i32,
+    pub max_health: i32,
+    pub max_mana: i32,
+}
+
+impl std::ops::AddAssign for StatBonus {
+    fn add_assign(&mut self, other: Self) {
+        self.attack += other.attack;
+        self.defence += other.defence;
+        self.max_health += other.max_health;
+        self.max_mana += other.max_mana;
+    }
+}
+
+/// The sum of a character's temporary stat bonuses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Buffs(pub StatBonus);
+
+/// A character's stats once their class, level, equipment and buffs are
+/// all accounted for. These are worked out again every frame, so they
+/// should be read rather than changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct EffectiveStats {
+    /// Added to the damage of every hit the character lands
+    pub attack: u32,
+    /// Taken off the damage of every hit, though hits always do at least one damage
+    pub defence: u32,
+    pub max_health: u32,
+    pub max_mana: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+
+/// Every equipment slot, in the order they're shown on the inventory screen
+pub const EQUIPMENT_SLOTS: [EquipmentSlot; 3] = [
+    EquipmentSlot::Weapon,
+    EquipmentSlot::Armor,
+    EquipmentSlot::Accessory,
+];
+
+impl EquipmentSlot {
+    /// The slot items of the given category are equipped in, if they can be
+    pub fn for_category(category: ItemCategory) -> Option<EquipmentSlot> {
+        match category {
+            ItemCategory::Weapon => Some(EquipmentSlot::Weapon),
+            ItemCategory::Armor => Some(EquipmentSlot::Armor),
+            ItemCategory::Accessory => Some(EquipmentSlot::Accessory),
+            _ => None,
+        }
+    }
+}
+
+/// The items a character has equipped, by id
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Equipment {
+    pub weapon: Option<String>,
+    pub armor: Option<String>,
+    pub accessory: Option<String>,
+}
+
+impl Equipment {
+    pub fn slot(&self, slot: EquipmentSlot) -> &Option<String> {
+        match slot {
+            EquipmentSlot::Weapon => &self.weapon,
+            EquipmentSlot::Armor => &self.armor,
+            EquipmentSlot::Accessory => &self.accessory,
+        }
+    }
+
+    pub fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<String> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Accessory => &mut self.accessory,
+        }
+    }
+}
+
+/// Lingering effects that can be inflicted on characters. What
+/// each does is described by the status effect data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Status {
+    Burning,
+    Slow,
+    Poison,
+    Regeneration,
+    Might,
+    Fortitude,
+}
+
+/// A status effect that's active on a character
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StatusEffect {
+    pub status: Status,
+    /// Whoever inflicted the effect, who's credited with any kill it makes
+    pub source: Option<Entity>,
+    pub frames_left: u32,
+    /// How many times over the effect applies, for effects that stack
+    pub stacks: u32,
+    /// Frames until the effect next deals damage or heals
+    pub tick: u32,
+}
+
+/// Every status effect active on a character
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+/// A request to inflict `status` on the `target` entity
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatusEvent {
+    pub target: Entity,
+    pub source: Option<Entity>,
+    pub status: Status,
+}
+
+pub type StatusQueue = Vec<StatusEvent>;
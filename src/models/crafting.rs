@@ -0,0 +1,250 @@
+use super::components::*;
+use super::inventory;
+use super::player::facing_tile;
+use super::skills::{SkillInfo, Unlock};
+use super::zone::object_rect;
+use crate::map::TileMap;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::ItemInfo;
+use legion::systems::CommandBuffer;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn first_level() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecipeDef {
+    /// The items used up, and how many of each
+    pub inputs: Vec<(String, u32)>,
+    /// The items made, and how many of each. The first is
+    /// the one the crafting menu shows the recipe as.
+    pub outputs: Vec<(String, u32)>,
+    /// The skill the recipe needs and trains
+    pub skill: Skill,
+    /// The level of the skill needed to make the recipe
+    #[serde(default = "first_level")]
+    pub level: u32,
+    /// The kind of station the recipe is made at, or `None` if it can be made anywhere
+    #[serde(default)]
+    pub station: Option<String>,
+    /// The experience in `skill` making the recipe is worth
+    pub experience: u32,
+}
+
+impl RecipeDef {
+    /// How many times the recipe can be made out of what's in the inventory
+    pub fn batches(&self, inventory: &Inventory) -> u32 {
+        self.inputs
+            .iter()
+            .map(|(item, count)| inventory.count(item) / (*count).max(1))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Every recipe by name, and the name shown for each kind of station
+#[derive(Deserialize, Serialize)]
+pub struct RecipeInfo {
+    pub stations: HashMap<String, String>,
+    pub recipes: HashMap<String, RecipeDef>,
+}
+
+impl RecipeInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let recipe_info = ron::from_str(&contents)?;
+        Ok(recipe_info)
+    }
+
+    /// The recipes that can be made at a station (or without one) by a
+    /// player with the given skills out of what's in their inventory, in
+    /// order of name, along with how many times each can be made
+    pub fn craftable<'a>(
+        &'a self,
+        station: Option<&str>,
+        inventory: &Inventory,
+        skills: &Skills,
+        skill_info: &SkillInfo,
+    ) -> Vec<(&'a str, &'a RecipeDef, u32)> {
+        let mut recipes: Vec<_> = self
+            .recipes
+            .iter()
+            .filter(|(_, def)| def.station.is_none() || def.station.as_deref() == station)
+            .filter(|(_, def)| skills.level(def.skill) >= def.level)
+            .filter(|(name, _)| skill_info.allows(skills, &Unlock::Recipe(name.to_string())))
+            .map(|(name, def)| (name.as_str(), def, def.batches(inventory)))
+            .filter(|(_, _, batches)| *batches > 0)
+            .collect();
+
+        recipes.sort_by_key(|(name, _, _)| *name);
+        recipes
+    }
+}
+
+/// Legion systems for crafting and cooking
+pub mod systems {
+    use super::*;
+
+    /// Opens the crafting menu for players who ask to interact while facing
+    /// a station, which is a tiled object of kind `station` named after the
+    /// kind of station it is, or who ask to craft without one
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn use_stations(
+        entity: &Entity,
+        _: &IsPlayerCharacter,
+        position: &Position,
+        hurtbox: &Hurtbox,
+        crafting: Option<&Crafting>,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] map: &TileMap,
+    ) {
+        if crafting.is_some() {
+            return;
+        }
+
+        let station = if input.contains(&Input::ToggleCrafting) {
+            input.retain(|input| *input != Input::ToggleCrafting);
+            None
+        } else if input.contains(&Input::Interact) {
+            let tile = facing_tile(position, hurtbox, map);
+            let center = map.tile_center(tile.0, tile.1);
+            match map
+                .objects_of_kind("station")
+                .find(|station| object_rect(station).contains_point(center))
+            {
+                Some(station) => {
+                    input.retain(|input| *input != Input::Interact);
+                    Some(station.name.clone())
+                }
+                None => return,
+            }
+        } else {
+            return;
+        };
+
+        commands.add_component(
+            *entity,
+            Crafting {
+                station,
+                selected: 0,
+            },
+        );
+    }
+
+    /// Makes recipes for players with the crafting menu open, one at a time.
+    /// What's made goes into the inventory, or at the player's feet if there's
+    /// no room, and is worth experience in the recipe's skill. Players can't do
+    /// anything else until they leave.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn craft(
+        entity: &Entity,
+        position: &Position,
+        crafting: &mut Crafting,
+        inventory: &mut Inventory,
+        skills: &Skills,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] skill_experience_queue: &mut SkillExperienceQueue,
+        #[resource] recipe_info: &RecipeInfo,
+        #[resource] skill_info: &SkillInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let pressed = std::mem::take(input);
+
+        for input in pressed {
+            let recipes =
+                recipe_info.craftable(crafting.station.as_deref(), inventory, skills, skill_info);
+
+            match input {
+                Input::Back | Input::ToggleCrafting | Input::ToggleInventory => {
+                    commands.remove_component::<Crafting>(*entity);
+                    return;
+                }
+                Input::Navigate(Direction::Up) => {
+                    crafting.selected = crafting.selected.saturating_sub(1);
+                }
+                Input::Navigate(Direction::Down) => {
+                    crafting.selected =
+                        (crafting.selected + 1).min(recipes.len().saturating_sub(1));
+                }
+                Input::UseItem | Input::Interact => {
+                    let def = match recipes.get(crafting.selected) {
+                        Some((_, def, _)) => *def,
+                        None => continue,
+                    };
+
+                    for (item, count) in def.inputs.iter() {
+                        inventory.remove(item, *count);
+                    }
+                    for (item, count) in def.outputs.iter() {
+                        let stack_size = item_info.get(item).map_or(1, |def| def.stack_size);
+                        let left = inventory.add(item, *count, stack_size);
+                        if left > 0 {
+                            inventory::spawn_ground_item(
+                                commands,
+                                item,
+                                left,
+                                (position.x, position.y),
+                                item_info,
+                            );
+                        }
+                    }
+                    skill_experience_queue.push(SkillExperienceEvent {
+                        target: *entity,
+                        skill: def.skill,
+                        amount: def.experience,
+                    });
+
+                    // Making the last batch of a recipe takes it off the list
+                    let rows = recipe_info
+                        .craftable(crafting.station.as_deref(), inventory, skills, skill_info)
+                        .len();
+                    crafting.selected = crafting.selected.min(rows.saturating_sub(1));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Puts up the crafting menu for each player who has it open
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_crafting(
+        _: &IsPlayerCharacter,
+        crafting: &Crafting,
+        inventory: &Inventory,
+        skills: &Skills,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] recipe_info: &RecipeInfo,
+        #[resource] skill_info: &SkillInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let name = crafting
+            .station
+            .as_ref()
+            .and_then(|station| recipe_info.stations.get(station))
+            .map_or("Crafting", |name| name.as_str());
+
+        let rows = recipe_info
+            .craftable(crafting.station.as_deref(), inventory, skills, skill_info)
+            .into_iter()
+            .filter_map(|(_, def, batches)| {
+                let (item, count) = def.outputs.first()?;
+                let output = item_info.get(item)?;
+                Some((output.icon, output.name.clone(), *count, batches))
+            })
+            .collect();
+
+        render_queue.push(RenderRequest::Crafting {
+            name: String::from(name),
+            rows,
+            selected: crafting.selected,
+        });
+    }
+}
@@ -0,0 +1,400 @@
+use super::components::*;
+use super::inventory;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::ItemInfo;
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How close a player has to be to an NPC to talk to them, in pixels
+pub const TALK_RADIUS: i32 = 28;
+/// How many characters of a line are revealed each frame
+pub const TEXT_SPEED: usize = 2;
+/// How many branches can be followed in a row before we assume
+/// a dialogue tree loops back on itself and give up
+const MAX_BRANCHES: usize = 16;
+
+/// Something that has to be true of a player for a branch
+/// to be followed or a choice to be offered
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum DialogueCondition {
+    QuestNotStarted(String),
+    QuestActive(String),
+    QuestCompleted(String),
+    /// At least this many of the item
+    HasItem(String, u32),
+    /// At least this level
+    Level(u32),
+}
+
+/// Something that happens to a player when a line is said or a choice is made
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum DialogueAction {
+    GiveItem(String, u32),
+    TakeItem(String, u32),
+    StartQuest(String),
+    CompleteQuest(String),
+    /// Opens the named shop once the conversation's over
+    OpenShop(String),
+}
+
+/// Jumps straight to another line if every condition holds
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DialogueBranch {
+    pub conditions: Vec<DialogueCondition>,
+    pub next: String,
+}
+
+/// A reply the player can pick. Choices whose conditions
+/// don't hold aren't offered.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub conditions: Vec<DialogueCondition>,
+    #[serde(default)]
+    pub actions: Vec<DialogueAction>,
+    /// The line picking this leads to, or `None` to end the conversation
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+/// A single line of dialogue. The first of its `branches` that holds is
+/// followed before the line is said; otherwise its `actions` are taken
+/// and it's shown along with its choices. Lines without choices carry
+/// on to `next`, or end the conversation.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DialogueNode {
+    pub text: String,
+    #[serde(default)]
+    pub branches: Vec<DialogueBranch>,
+    #[serde(default)]
+    pub actions: Vec<DialogueAction>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+/// A conversation with an NPC, made up of named lines
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DialogueTree {
+    pub speaker: String,
+    /// The line every conversation starts from
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+/// Every dialogue tree, by name
+#[derive(Deserialize, Serialize)]
+pub struct DialogueInfo {
+    pub dialogues: HashMap<String, DialogueTree>,
+}
+
+impl DialogueInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let dialogue_info = ron::from_str(&contents)?;
+        Ok(dialogue_info)
+    }
+
+    /// The line a conversation is on, if both the tree and the line exist
+    pub fn node(&self, conversation: &Conversation) -> Option<(&DialogueTree, &DialogueNode)> {
+        let tree = self.dialogues.get(&conversation.dialogue)?;
+        let node = tree.nodes.get(&conversation.node)?;
+        Some((tree, node))
+    }
+}
+
+/// What a dialogue condition can be checked against
+pub struct Listener<'a> {
+    pub inventory: &'a Inventory,
+    pub quest_log: &'a QuestLog,
+    pub stats: &'a PlayerStats,
+}
+
+impl Listener<'_> {
+    pub fn meets(&self, condition: &DialogueCondition) -> bool {
+        use DialogueCondition::*;
+
+        match condition {
+            QuestNotStarted(quest) => self.quest_log.status(quest).is_none(),
+            QuestActive(quest) => self.quest_log.status(quest) == Some(QuestStatus::Active),
+            QuestCompleted(quest) => self.quest_log.status(quest) == Some(QuestStatus::Completed),
+            HasItem(item, count) => self.inventory.count(item) >= *count,
+            Level(level) => self.stats.level >= *level,
+        }
+    }
+
+    pub fn meets_all(&self, conditions: &[DialogueCondition]) -> bool {
+        conditions.iter().all(|condition| self.meets(condition))
+    }
+
+    /// The choices on a line that are offered to the player
+    pub fn choices<'n>(&self, node: &'n DialogueNode) -> Vec<&'n DialogueChoice> {
+        node.choices
+            .iter()
+            .filter(|choice| self.meets_all(&choice.conditions))
+            .collect()
+    }
+}
+
+/// Takes each action in turn. Items that don't fit in the
+/// inventory are dropped at the player's feet.
+fn act(
+    actions: &[DialogueAction],
+    player: Entity,
+    position: &Position,
+    inventory: &mut Inventory,
+    quest_log: &mut QuestLog,
+    commands: &mut CommandBuffer,
+    item_info: &ItemInfo,
+) {
+    for action in actions {
+        match action {
+            DialogueAction::GiveItem(item, count) => {
+                let stack_size = item_info.get(item).map_or(1, |def| def.stack_size);
+                let left = inventory.add(item, *count, stack_size);
+                if left > 0 {
+                    inventory::spawn_ground_item(
+                        commands,
+                        item,
+                        left,
+                        (position.x, position.y),
+                        item_info,
+                    );
+                }
+            }
+            DialogueAction::TakeItem(item, count) => {
+                inventory.remove(item, *count);
+            }
+            DialogueAction::StartQuest(quest) => quest_log.start(quest),
+            DialogueAction::CompleteQuest(quest) => quest_log.complete(quest),
+            DialogueAction::OpenShop(shop) => commands.add_component(player, Shopping::new(shop)),
+        }
+    }
+}
+
+/// Legion systems for talking to NPCs
+pub mod systems {
+    use super::*;
+
+    /// Starts a conversation with the nearest NPC that has something to say
+    /// when a player who isn't already talking to someone asks to interact.
+    /// Without anyone to talk to, the request is left for something else.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Conversation)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[read_component(Npc)]
+    pub fn talk_to_npcs(
+        world: &SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] dialogue_info: &DialogueInfo,
+    ) {
+        if !input.contains(&Input::Interact) {
+            return;
+        }
+
+        for (player, position, hurtbox, _) in
+            <(Entity, &Position, &Hurtbox, &IsPlayerCharacter)>::query()
+                .filter(!component::<Conversation>())
+                .iter(world)
+        {
+            let center = hurtbox.at(position).center();
+            let distance = |position: &Position| {
+                let (dx, dy) = (position.x + 8 - center.x(), position.y + 8 - center.y());
+                dx * dx + dy * dy
+            };
+
+            let nearest = <(Entity, &Position, &Npc)>::query()
+                .iter(world)
+                .filter_map(|(npc, position, Npc { dialogue })| {
+                    Some((*npc, position, dialogue.as_ref()?))
+                })
+                .filter(|(_, position, _)| distance(position) <= TALK_RADIUS * TALK_RADIUS)
+                .min_by_key(|(_, position, _)| distance(position));
+
+            if let Some((npc, _, dialogue)) = nearest {
+                input.retain(|input| *input != Input::Interact);
+                match dialogue_info.dialogues.get(dialogue) {
+                    Some(tree) => commands
+                        .add_component(*player, Conversation::new(npc, dialogue, &tree.start)),
+                    None => println!("No dialogue named {}", dialogue),
+                }
+            }
+        }
+    }
+
+    /// Moves each conversation along: follows branches and takes the actions
+    /// of lines as they're reached, reveals them a few characters at a time,
+    /// and lets the player skip ahead, pick a choice or carry on. Players
+    /// can't do anything else while they're talking. A [TalkEvent] is raised
+    /// when a conversation comes to an end.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn converse(
+        entity: &Entity,
+        position: &Position,
+        conversation: &mut Conversation,
+        inventory: &mut Inventory,
+        quest_log: &mut QuestLog,
+        stats: &PlayerStats,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] talk_queue: &mut TalkQueue,
+        #[resource] dialogue_info: &DialogueInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let pressed = std::mem::take(input);
+
+        if !conversation.entered {
+            for _ in 0..MAX_BRANCHES {
+                let listener = Listener {
+                    inventory,
+                    quest_log,
+                    stats,
+                };
+                let branch = dialogue_info.node(conversation).and_then(|(_, node)| {
+                    node.branches
+                        .iter()
+                        .find(|branch| listener.meets_all(&branch.conditions))
+                });
+
+                match branch {
+                    Some(branch) => conversation.go_to(&branch.next),
+                    None => break,
+                }
+            }
+
+            let node = match dialogue_info.node(conversation) {
+                Some((_, node)) => node,
+                None => {
+                    println!(
+                        "No line named {} in dialogue {}",
+                        conversation.node, conversation.dialogue
+                    );
+                    commands.remove_component::<Conversation>(*entity);
+                    return;
+                }
+            };
+
+            act(
+                &node.actions,
+                *entity,
+                position,
+                inventory,
+                quest_log,
+                commands,
+                item_info,
+            );
+            conversation.entered = true;
+        }
+
+        let node = match dialogue_info.node(conversation) {
+            Some((_, node)) => node,
+            None => return,
+        };
+
+        let length = node.text.chars().count();
+        let revealed = conversation.shown >= length;
+        conversation.shown = (conversation.shown + TEXT_SPEED).min(length);
+
+        let choices = Listener {
+            inventory,
+            quest_log,
+            stats,
+        }
+        .choices(node);
+        conversation.selected = conversation.selected.min(choices.len().saturating_sub(1));
+
+        for input in pressed {
+            match input {
+                Input::Navigate(Direction::Up) if revealed => {
+                    conversation.selected = conversation.selected.saturating_sub(1);
+                }
+                Input::Navigate(Direction::Down) if revealed && !choices.is_empty() => {
+                    conversation.selected = (conversation.selected + 1).min(choices.len() - 1);
+                }
+                Input::Interact | Input::UseItem if !revealed => {
+                    // Skip to the end of the line
+                    conversation.shown = length;
+                }
+                Input::Interact | Input::UseItem => {
+                    let next = match choices.get(conversation.selected) {
+                        Some(choice) => {
+                            act(
+                                &choice.actions,
+                                *entity,
+                                position,
+                                inventory,
+                                quest_log,
+                                commands,
+                                item_info,
+                            );
+                            choice.next.clone()
+                        }
+                        None => node.next.clone(),
+                    };
+
+                    match next {
+                        Some(next) => conversation.go_to(&next),
+                        None => {
+                            talk_queue.push(TalkEvent {
+                                player: *entity,
+                                dialogue: conversation.dialogue.clone(),
+                            });
+                            commands.remove_component::<Conversation>(*entity);
+                        }
+                    }
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Puts the line each player is being told in a box at the bottom of
+    /// the screen, with their choices underneath once it's all been said
+    #[system(for_each)]
+    pub fn draw_dialogue(
+        _: &IsPlayerCharacter,
+        conversation: &Conversation,
+        inventory: &Inventory,
+        quest_log: &QuestLog,
+        stats: &PlayerStats,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] dialogue_info: &DialogueInfo,
+    ) {
+        let (tree, node) = match dialogue_info.node(conversation) {
+            Some(line) => line,
+            None => return,
+        };
+
+        let choices = if conversation.shown >= node.text.chars().count() {
+            Listener {
+                inventory,
+                quest_log,
+                stats,
+            }
+            .choices(node)
+            .iter()
+            .map(|choice| choice.text.clone())
+            .collect()
+        } else {
+            vec![]
+        };
+
+        render_queue.push(RenderRequest::Dialogue {
+            speaker: tree.speaker.clone(),
+            text: node.text.clone(),
+            shown: conversation.shown,
+            choices,
+            selected: conversation.selected,
+        });
+    }
+}
@@ -0,0 +1,334 @@
+use super::components::*;
+use super::projectile;
+use super::status;
+use crate::map::pathfinding::find_path;
+use crate::map::TileMap;
+use crate::util::{Rng, StatusInfo};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use sdl2::rect::Point;
+use std::collections::HashMap;
+
+/// How long an enemy's attack locks it in place
+const ATTACK_FRAMES: u32 = 8;
+/// How often a chasing enemy recomputes its path to its target
+const REPATH_FRAMES: u32 = 10;
+/// The arc of an enemy's melee attack, in degrees
+const ATTACK_ARC: f32 = 90.0;
+
+fn distance(a: Point, b: Point) -> u32 {
+    let (dx, dy) = ((a.x() - b.x()) as f32, (a.y() - b.y()) as f32);
+    (dx * dx + dy * dy).sqrt() as u32
+}
+
+/// The direction that most closely points from `from` to `to`
+fn facing(from: Point, to: Point) -> Direction {
+    let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+
+    if dx.abs() > dy.abs() {
+        if dx < 0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    } else if dy < 0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
+/// Moves an entity whose centre is at `from` up to `speed` pixels
+/// towards `to`, one axis at a time so that it only ever walks in
+/// the four directions it has sprites for. Returns whether it arrived.
+pub fn step_towards(position: &mut Position, from: Point, to: Point, speed: i32) -> bool {
+    let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+
+    if dx == 0 && dy == 0 {
+        return true;
+    }
+
+    position.direction = facing(from, to);
+    if dx.abs() > dy.abs() {
+        position.x += dx.signum() * speed.min(dx.abs());
+    } else {
+        position.y += dy.signum() * speed.min(dy.abs());
+    }
+
+    false
+}
+
+/// Walks an entity along its path. Returns true once the path is used up.
+fn follow_path(
+    ai: &mut Ai,
+    position: &mut Position,
+    center: Point,
+    speed: i32,
+    map: &TileMap,
+) -> bool {
+    while let Some(&(x, y)) = ai.path.first() {
+        let (waypoint_x, waypoint_y) = map.tile_center(x, y);
+        if step_towards(position, center, Point::new(waypoint_x, waypoint_y), speed) {
+            ai.path.remove(0);
+        } else {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Legion systems that drive enemies
+pub mod systems {
+    use super::*;
+
+    /// Decides what each enemy does this frame. Enemies idle and wander
+    /// around their spawn point until a player comes within their aggro
+    /// radius, chase and attack that player, and head home again once
+    /// they're led too far away or lose their target.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Hurtbox)]
+    #[read_component(SpawnPoint)]
+    #[read_component(StatusEffects)]
+    #[write_component(Position)]
+    #[write_component(PlayerState)]
+    #[write_component(StateTimer)]
+    #[write_component(EnemyStats)]
+    #[write_component(Ai)]
+    pub fn think(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] map: &TileMap,
+        #[resource] rng: &mut Rng,
+        #[resource] status_info: &StatusInfo,
+    ) {
+        let speed_percents: HashMap<Entity, u32> = <(Entity, &StatusEffects)>::query()
+            .iter(world)
+            .map(|(entity, effects)| (*entity, status::speed_percent(effects, status_info)))
+            .collect();
+
+        let players: Vec<(Entity, Point)> = <(
+            Entity,
+            &Position,
+            &Hurtbox,
+            &PlayerState,
+            &IsPlayerCharacter,
+        )>::query()
+        .iter(world)
+        .filter(|(_, _, _, state, _)| **state != PlayerState::Death)
+        .map(|(entity, position, hurtbox, _, _)| (*entity, hurtbox.at(position).center()))
+        .collect();
+
+        let mut enemies = <(
+            Entity,
+            &mut Position,
+            &Hurtbox,
+            &SpawnPoint,
+            &mut EnemyStats,
+            &mut PlayerState,
+            &mut StateTimer,
+            &mut Ai,
+        )>::query();
+
+        for (entity, position, hurtbox, spawn, stats, state, timer, ai) in enemies.iter_mut(world) {
+            // Attacking, flinching and dying all have to play out first
+            if timer.0 > 0 {
+                continue;
+            }
+
+            ai.cooldown = ai.cooldown.saturating_sub(1);
+            ai.timer = ai.timer.saturating_sub(1);
+
+            // Slowed enemies still crawl along rather than stopping dead
+            let speed = match speed_percents.get(entity) {
+                Some(percent) => (stats.speed * *percent as i32 / 100).max(1),
+                None => stats.speed,
+            };
+
+            let center = hurtbox.at(position).center();
+            let home = hurtbox
+                .at(&Position {
+                    x: spawn.x,
+                    y: spawn.y,
+                    ..*position
+                })
+                .center();
+            let target_of = |target: Entity| {
+                players
+                    .iter()
+                    .find(|(player, _)| *player == target)
+                    .map(|(_, point)| *point)
+            };
+
+            // First work out whether anything should change what we're doing ...
+            ai.state = match ai.state {
+                AiState::Idle | AiState::Wander => {
+                    let nearest = players
+                        .iter()
+                        .filter(|(_, point)| distance(center, *point) <= ai.profile.aggro_radius)
+                        .min_by_key(|(_, point)| distance(center, *point));
+
+                    match nearest {
+                        Some((player, _)) => {
+                            ai.path.clear();
+                            ai.timer = 0;
+                            AiState::Chase(*player)
+                        }
+                        None => ai.state,
+                    }
+                }
+                AiState::Chase(target) | AiState::Attack(target) => match target_of(target) {
+                    Some(point) if distance(center, home) <= ai.profile.leash_radius => {
+                        if distance(center, point) <= stats.attack_range {
+                            AiState::Attack(target)
+                        } else {
+                            AiState::Chase(target)
+                        }
+                    }
+                    _ => {
+                        ai.path.clear();
+                        AiState::Leash
+                    }
+                },
+                AiState::Leash => ai.state,
+            };
+
+            // ... then do it
+            *state = PlayerState::Idle;
+            match ai.state {
+                AiState::Idle => {
+                    if ai.timer == 0 {
+                        let (home_x, home_y) = map.tile_at(home.x(), home.y());
+                        let radius = (ai.profile.wander_radius / map.tile_dimensions.0) as i32;
+                        let destination = (
+                            home_x + rng.range(-radius, radius + 1),
+                            home_y + rng.range(-radius, radius + 1),
+                        );
+
+                        if let Some(path) =
+                            find_path(map, map.tile_at(center.x(), center.y()), destination)
+                        {
+                            ai.path = path;
+                            ai.state = AiState::Wander;
+                        }
+                        ai.timer = rng.range(20, 60) as u32;
+                    }
+                }
+                AiState::Wander => {
+                    *state = PlayerState::Moving;
+                    if follow_path(ai, position, center, speed / 2 + 1, map) {
+                        ai.state = AiState::Idle;
+                    }
+                }
+                AiState::Chase(target) => {
+                    let point = target_of(target).unwrap();
+
+                    if ai.timer == 0 || ai.path.is_empty() {
+                        ai.path = find_path(
+                            map,
+                            map.tile_at(center.x(), center.y()),
+                            map.tile_at(point.x(), point.y()),
+                        )
+                        .unwrap_or_default();
+                        ai.timer = REPATH_FRAMES;
+                    }
+
+                    *state = PlayerState::Moving;
+                    // Once we're on the same tile there's no path left
+                    // to walk, so close the last few pixels directly
+                    if follow_path(ai, position, center, speed, map) {
+                        step_towards(position, center, point, speed);
+                    }
+                }
+                AiState::Attack(target) => {
+                    let point = target_of(target).unwrap();
+                    position.direction = facing(center, point);
+
+                    if ai.cooldown == 0 {
+                        *state = PlayerState::Attack;
+                        timer.0 = ATTACK_FRAMES;
+                        ai.cooldown = stats.attack_cooldown;
+
+                        match &stats.projectile {
+                            Some(info) => {
+                                let heading = (
+                                    (point.x() - center.x()) as f32,
+                                    (point.y() - center.y()) as f32,
+                                );
+                                projectile::spawn(
+                                    commands,
+                                    *entity,
+                                    (center.x(), center.y()),
+                                    heading,
+                                    info,
+                                );
+                            }
+                            None => {
+                                commands.push((Hitbox {
+                                    owner: *entity,
+                                    origin: (center.x(), center.y()),
+                                    direction: position.direction,
+                                    reach: stats.attack_range + 4,
+                                    arc: ATTACK_ARC,
+                                    damage: stats.damage,
+                                    status: None,
+                                    frames_left: 1,
+                                    struck: vec![],
+                                },));
+                            }
+                        }
+                    }
+                }
+                AiState::Leash => {
+                    if ai.path.is_empty() {
+                        ai.path = find_path(
+                            map,
+                            map.tile_at(center.x(), center.y()),
+                            map.tile_at(home.x(), home.y()),
+                        )
+                        .unwrap_or_default();
+                    }
+
+                    *state = PlayerState::Moving;
+                    if follow_path(ai, position, center, speed, map) {
+                        // Back home, so patch ourselves up and settle down
+                        stats.health.refill();
+                        ai.state = AiState::Idle;
+                        ai.timer = rng.range(20, 60) as u32;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts down enemy attacks, flinching and death. Enemies that
+    /// have finished dying are removed from the world.
+    #[system(for_each)]
+    pub fn recover(
+        entity: &Entity,
+        _: &EnemyStats,
+        timer: &mut StateTimer,
+        state: &mut PlayerState,
+        commands: &mut CommandBuffer,
+    ) {
+        if timer.0 == 0 {
+            return;
+        }
+
+        timer.0 -= 1;
+        if timer.0 > 0 {
+            return;
+        }
+
+        match *state {
+            PlayerState::Death => {
+                commands.remove(*entity);
+            }
+            _ => {
+                *state = PlayerState::Idle;
+            }
+        }
+    }
+}
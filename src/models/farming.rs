@@ -0,0 +1,447 @@
+use super::clock::WorldClock;
+use super::components::*;
+use super::inventory;
+use super::player::facing_tile;
+use super::skills::{SkillInfo, Unlock};
+use super::zone::{object_rect, Zone};
+use crate::map::autotile::AutotileInfo;
+use crate::map::TileMap;
+use crate::util::{ItemInfo, Rng};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How long tilling, planting, watering and harvesting take,
+/// which is as long as the animation for them plays for
+pub const TEND_FRAMES: u32 = 8;
+/// The terrain tilled tiles are painted with
+pub const SOIL: &str = "soil";
+/// The terrain tilled tiles are painted with on days they've been watered
+pub const WATERED_SOIL: &str = "watered_soil";
+/// The map layer farmland is painted onto
+const FARM_LAYER: usize = 0;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CropDef {
+    /// The item planted to grow the crop
+    pub seed: String,
+    /// How many watered days the crop spends at each
+    /// stage of growing, after which it's ripe
+    pub days: Vec<u32>,
+    /// The crop's first sprite in the `crops` sheet. The sprites for
+    /// each stage follow it, with the ripe crop's sprite last.
+    pub sprite: usize,
+    /// The item harvesting the crop gives
+    pub harvest: String,
+    /// The fewest and most of the item harvesting gives
+    pub yields: (u32, u32),
+    /// The farming experience harvesting is worth
+    pub experience: u32,
+}
+
+impl CropDef {
+    pub fn ripe(&self, crop: &Crop) -> bool {
+        crop.stage >= self.days.len()
+    }
+
+    /// Grows a crop by a watered day, moving it on to its
+    /// next stage once it's spent long enough at this one
+    pub fn grow(&self, crop: &mut Crop) {
+        if self.ripe(crop) {
+            return;
+        }
+
+        crop.days += 1;
+        if crop.days >= self.days[crop.stage] {
+            crop.stage += 1;
+            crop.days = 0;
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FarmingInfo {
+    /// Every crop, by name
+    pub crops: HashMap<String, CropDef>,
+}
+
+impl FarmingInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let farming_info = ron::from_str(&contents)?;
+        Ok(farming_info)
+    }
+
+    pub fn get(&self, crop: &str) -> Option<&CropDef> {
+        self.crops.get(crop)
+    }
+
+    /// The name of the crop a seed grows into, if it's a seed at all
+    pub fn crop_for_seed(&self, seed: &str) -> Option<&str> {
+        self.crops
+            .iter()
+            .find(|(_, def)| def.seed == seed)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// A tilled tile
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Plot {
+    /// Whether it's been watered today
+    pub watered: bool,
+}
+
+impl Plot {
+    fn terrain(&self) -> &'static str {
+        if self.watered {
+            WATERED_SOIL
+        } else {
+            SOIL
+        }
+    }
+}
+
+/// The farmland of one zone
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Farm {
+    /// Every tilled tile, by its tile coordinates
+    pub plots: HashMap<(i32, i32), Plot>,
+    /// The zone's crops while nobody's there. While the zone's
+    /// loaded its crops are entities, and this is empty.
+    pub crops: Vec<Crop>,
+}
+
+/// Every zone's farmland, by [ZoneMap::key](super::zone::ZoneMap::key), and
+/// the day crops last grew on. Farms are kept while nobody's around to tend
+/// them, so this lives outside of any zone.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Farms {
+    pub day: u64,
+    pub zones: HashMap<String, Farm>,
+}
+
+/// The components of a crop entity, which sits on the tile it's planted on
+fn crop_components(
+    crop: Crop,
+    farming_info: &FarmingInfo,
+    map: &TileMap,
+) -> (Position, StaticSprite, Crop) {
+    let (tile_width, tile_height) = map.tile_dimensions;
+    let sprite = farming_info.get(&crop.name).map_or(0, |def| def.sprite);
+
+    (
+        Position {
+            x: crop.tile.0 * tile_width as i32,
+            y: crop.tile.1 * tile_height as i32,
+            velocity: 0,
+            direction: Direction::Down,
+        },
+        StaticSprite {
+            sheet: String::from("crops"),
+            index: sprite + crop.stage,
+        },
+        crop,
+    )
+}
+
+/// Puts the crops of the zone being left into its farm,
+/// so they're still there when players come back
+pub fn store_crops(world: &World, farms: &mut Farms, zone: &str) {
+    let crops: Vec<Crop> = <&Crop>::query().iter(world).cloned().collect();
+    if crops.is_empty() {
+        return;
+    }
+
+    farms
+        .zones
+        .entry(String::from(zone))
+        .or_default()
+        .crops
+        .extend(crops);
+}
+
+/// Lays a zone's farm back over its freshly loaded map,
+/// tilling its plots and turning its crops back into entities
+pub fn restore_farm(
+    world: &mut World,
+    farms: &mut Farms,
+    zone: &str,
+    map: &mut TileMap,
+    autotile_info: &AutotileInfo,
+    farming_info: &FarmingInfo,
+) {
+    let farm = match farms.zones.get_mut(zone) {
+        Some(farm) => farm,
+        None => return,
+    };
+
+    for (tile, plot) in farm.plots.iter() {
+        autotile_info.paint(map, FARM_LAYER, tile.0, tile.1, plot.terrain());
+    }
+    for crop in farm.crops.drain(..) {
+        world.push(crop_components(crop, farming_info, map));
+    }
+}
+
+/// The inventory slot of the seed to plant and the crop it grows into: the
+/// selected item if it's a seed, or otherwise the first seed in the inventory.
+/// Seeds the player's skills aren't far enough along for are passed over.
+fn seed_to_plant<'a>(
+    inventory: &Inventory,
+    skills: &Skills,
+    farming_info: &'a FarmingInfo,
+    skill_info: &SkillInfo,
+) -> Option<(usize, &'a str)> {
+    let crop_in = |slot: usize| {
+        let stack = inventory.slots.get(slot)?.as_ref()?;
+        if !skill_info.allows(skills, &Unlock::Item(stack.item.clone())) {
+            return None;
+        }
+        Some((slot, farming_info.crop_for_seed(&stack.item)?))
+    };
+
+    crop_in(inventory.selected).or_else(|| (0..inventory.slots.len()).find_map(crop_in))
+}
+
+/// Plays the attack animation for a bit of farm work, locking the player in place
+fn tend(input: &mut InputQueue, state: &mut PlayerState, timer: &mut StateTimer) {
+    input.retain(|input| *input != Input::Interact);
+    *state = PlayerState::Attack;
+    timer.0 = TEND_FRAMES;
+}
+
+/// Legion systems for farming
+pub mod systems {
+    use super::*;
+
+    /// Harvests the ripe crop in front of players who ask to interact.
+    /// Harvests that don't fit in the inventory are dropped at the player's feet.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[read_component(Crop)]
+    #[write_component(Inventory)]
+    #[write_component(PlayerState)]
+    #[write_component(StateTimer)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn harvest_crops(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] skill_experience_queue: &mut SkillExperienceQueue,
+        #[resource] rng: &mut Rng,
+        #[resource] map: &TileMap,
+        #[resource] farming_info: &FarmingInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        if !input.contains(&Input::Interact) {
+            return;
+        }
+
+        let mut ripe: Vec<(Entity, (i32, i32), &CropDef)> = <(Entity, &Crop)>::query()
+            .iter(world)
+            .filter_map(|(entity, crop)| {
+                let def = farming_info.get(&crop.name)?;
+                Some((*entity, crop.tile, def)).filter(|_| def.ripe(crop))
+            })
+            .collect();
+
+        for (entity, position, hurtbox, inventory, state, timer, _) in <(
+            Entity,
+            &Position,
+            &Hurtbox,
+            &mut Inventory,
+            &mut PlayerState,
+            &mut StateTimer,
+            &IsPlayerCharacter,
+        )>::query()
+        .iter_mut(world)
+        {
+            if timer.0 > 0 {
+                continue;
+            }
+
+            let tile = facing_tile(position, hurtbox, map);
+            let (crop, def) = match ripe.iter().position(|(_, crop_tile, _)| *crop_tile == tile) {
+                Some(index) => {
+                    let (crop, _, def) = ripe.remove(index);
+                    (crop, def)
+                }
+                None => continue,
+            };
+            commands.remove(crop);
+
+            let count = rng.range(def.yields.0 as i32, def.yields.1 as i32 + 1) as u32;
+            let stack_size = item_info.get(&def.harvest).map_or(1, |def| def.stack_size);
+            let left = inventory.add(&def.harvest, count, stack_size);
+            if left > 0 {
+                inventory::spawn_ground_item(
+                    commands,
+                    &def.harvest,
+                    left,
+                    (position.x, position.y),
+                    item_info,
+                );
+            }
+            skill_experience_queue.push(SkillExperienceEvent {
+                target: *entity,
+                skill: Skill::Farming,
+                amount: def.experience,
+            });
+
+            tend(input, state, timer);
+        }
+    }
+
+    /// Tends the tile in front of players who ask to interact while facing
+    /// farmland: untilled ground is tilled, empty soil is planted with a seed
+    /// from the inventory, and dry soil is watered
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[read_component(Crop)]
+    #[read_component(Skills)]
+    #[write_component(Inventory)]
+    #[write_component(PlayerState)]
+    #[write_component(StateTimer)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn tend_farms(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] map: &mut TileMap,
+        #[resource] zone: &Zone,
+        #[resource] farms: &mut Farms,
+        #[resource] autotile_info: &AutotileInfo,
+        #[resource] farming_info: &FarmingInfo,
+        #[resource] skill_info: &SkillInfo,
+    ) {
+        if !input.contains(&Input::Interact) {
+            return;
+        }
+
+        let mut planted: Vec<(i32, i32)> =
+            <&Crop>::query().iter(world).map(|crop| crop.tile).collect();
+
+        for (position, hurtbox, inventory, skills, state, timer, _) in <(
+            &Position,
+            &Hurtbox,
+            &mut Inventory,
+            &Skills,
+            &mut PlayerState,
+            &mut StateTimer,
+            &IsPlayerCharacter,
+        )>::query()
+        .iter_mut(world)
+        {
+            if timer.0 > 0 {
+                continue;
+            }
+
+            let tile = facing_tile(position, hurtbox, map);
+            let center = map.tile_center(tile.0, tile.1);
+            if !map
+                .objects_of_kind("farm")
+                .any(|farm| object_rect(farm).contains_point(center))
+            {
+                continue;
+            }
+
+            let farm = farms.zones.entry(String::from(zone.map.key())).or_default();
+            let seed = seed_to_plant(inventory, skills, farming_info, skill_info);
+
+            match (
+                farm.plots.get(&tile).copied(),
+                planted.contains(&tile),
+                seed,
+            ) {
+                (None, ..) => {
+                    if map.is_solid(tile.0, tile.1) || map.is_water(tile.0, tile.1) {
+                        continue;
+                    }
+                    farm.plots.insert(tile, Plot::default());
+                    autotile_info.paint(map, FARM_LAYER, tile.0, tile.1, SOIL);
+                }
+                (Some(_), false, Some((slot, name))) => {
+                    inventory.take(slot, 1);
+                    planted.push(tile);
+
+                    let crop = Crop {
+                        name: String::from(name),
+                        tile,
+                        stage: 0,
+                        days: 0,
+                    };
+                    commands.push(crop_components(crop, farming_info, map));
+                }
+                (Some(plot), ..) if !plot.watered => {
+                    farm.plots.insert(tile, Plot { watered: true });
+                    autotile_info.paint(map, FARM_LAYER, tile.0, tile.1, WATERED_SOIL);
+                }
+                _ => continue,
+            }
+
+            tend(input, state, timer);
+        }
+    }
+
+    /// Grows every crop that was watered once a new day comes around,
+    /// including the crops of zones nobody's in, then dries out the soil
+    #[system]
+    #[write_component(Crop)]
+    #[write_component(StaticSprite)]
+    pub fn grow_crops(
+        world: &mut SubWorld,
+        #[resource] clock: &WorldClock,
+        #[resource] zone: &Zone,
+        #[resource] farms: &mut Farms,
+        #[resource] map: &mut TileMap,
+        #[resource] autotile_info: &AutotileInfo,
+        #[resource] farming_info: &FarmingInfo,
+    ) {
+        if clock.day() <= farms.day {
+            return;
+        }
+        farms.day = clock.day();
+
+        let here = zone.map.key();
+        for (crop, sprite) in <(&mut Crop, &mut StaticSprite)>::query().iter_mut(world) {
+            let watered = farms
+                .zones
+                .get(here)
+                .and_then(|farm| farm.plots.get(&crop.tile))
+                .is_some_and(|plot| plot.watered);
+
+            if let Some(def) = farming_info.get(&crop.name) {
+                if watered {
+                    def.grow(crop);
+                }
+                sprite.index = def.sprite + crop.stage;
+            }
+        }
+
+        for (key, farm) in farms.zones.iter_mut() {
+            for crop in farm.crops.iter_mut() {
+                let watered = farm.plots.get(&crop.tile).is_some_and(|plot| plot.watered);
+                if let (true, Some(def)) = (watered, farming_info.get(&crop.name)) {
+                    def.grow(crop);
+                }
+            }
+
+            for (tile, plot) in farm.plots.iter_mut() {
+                if !plot.watered {
+                    continue;
+                }
+                plot.watered = false;
+                if key == here {
+                    autotile_info.paint(map, FARM_LAYER, tile.0, tile.1, SOIL);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use super::clock::{TimeOfDay, WorldClock};
+use super::components::*;
+use super::inventory;
+use super::player::facing_tile;
+use super::zone::Zone;
+use crate::map::TileMap;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{ItemInfo, Rng};
+use legion::systems::CommandBuffer;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How long casting takes, which is as long as the cast animation plays for
+pub const CAST_FRAMES: u32 = 8;
+/// The shortest and longest waits for a bite
+pub const BITE_FRAMES: (u32, u32) = (40, 160);
+/// How long a fish stays on the line before it gets away
+pub const HOOK_FRAMES: u32 = 80;
+/// How wide the catching bar is
+pub const BAR_WIDTH: i32 = 100;
+
+/// Something that can be caught
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Catch {
+    pub item: String,
+    /// How likely this is to bite relative to everything else that could
+    pub weight: u32,
+    /// When it bites. It bites any time if this is empty.
+    #[serde(default)]
+    pub times: Vec<TimeOfDay>,
+    /// The fishing level needed before it bites at all
+    #[serde(default)]
+    pub level: u32,
+    /// How hard it is to catch, from 1 up. Harder catches
+    /// have smaller targets and faster markers.
+    pub difficulty: u32,
+    /// The fishing experience it's worth
+    pub experience: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct FishingInfo {
+    /// What can be caught in each zone, keyed by [ZoneMap::key](super::zone::ZoneMap::key)
+    pub tables: HashMap<String, Vec<Catch>>,
+}
+
+impl FishingInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let fishing_info = ron::from_str(&contents)?;
+        Ok(fishing_info)
+    }
+
+    /// Picks something to bite, weighted by how likely each catch is,
+    /// from the catches in the zone that bite at this time of day for
+    /// a fisher of the given level
+    pub fn bite(&self, zone: &str, time: TimeOfDay, level: u32, rng: &mut Rng) -> Option<&Catch> {
+        let catches: Vec<&Catch> = self
+            .tables
+            .get(zone)?
+            .iter()
+            .filter(|catch| catch.times.is_empty() || catch.times.contains(&time))
+            .filter(|catch| catch.level <= level)
+            .collect();
+
+        let total: u32 = catches.iter().map(|catch| catch.weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut pick = (rng.next_u64() % total as u64) as u32;
+        catches.into_iter().find(|catch| {
+            if pick < catch.weight {
+                return true;
+            }
+            pick -= catch.weight;
+            false
+        })
+    }
+}
+
+/// Hooks a catch, setting up the bar for catching it. Higher fishing
+/// levels widen the target, and harder catches narrow it and speed up the marker.
+fn hook(catch: &Catch, level: u32, rng: &mut Rng) -> FishingPhase {
+    let width = (36 + level as i32 * 2 - catch.difficulty as i32 * 6).clamp(8, BAR_WIDTH / 2);
+    let start = rng.range(0, BAR_WIDTH - width + 1);
+
+    FishingPhase::Hooked {
+        item: catch.item.clone(),
+        experience: catch.experience,
+        marker: 0,
+        speed: 2 + catch.difficulty as i32,
+        target: (start, start + width),
+    }
+}
+
+/// Legion systems for fishing
+pub mod systems {
+    use super::*;
+
+    /// Casts a line for players who ask to interact while facing water,
+    /// as long as they're not busy with something else
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn cast_line(
+        entity: &Entity,
+        _: &IsPlayerCharacter,
+        position: &Position,
+        hurtbox: &Hurtbox,
+        state: &mut PlayerState,
+        timer: &mut StateTimer,
+        fishing: Option<&Fishing>,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] map: &TileMap,
+    ) {
+        if fishing.is_some() || timer.0 > 0 || !input.contains(&Input::Interact) {
+            return;
+        }
+
+        let spot = facing_tile(position, hurtbox, map);
+        if !map.is_water(spot.0, spot.1) {
+            return;
+        }
+        input.retain(|input| *input != Input::Interact);
+
+        // Casting plays the attack animation, locking the player in place
+        *state = PlayerState::Attack;
+        timer.0 = CAST_FRAMES;
+
+        commands.add_component(
+            *entity,
+            Fishing {
+                spot,
+                phase: FishingPhase::Casting,
+                frames_left: CAST_FRAMES,
+            },
+        );
+    }
+
+    /// Runs each player's fishing: waits for a bite once the line's out,
+    /// hooks something from the zone's catch table, and plays out the catching
+    /// bar. Catches go into the inventory, or at the player's feet if there's
+    /// no room, and are worth fishing experience. Moving or backing out reels
+    /// the line in, and players can't do anything else while they're fishing.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn fish(
+        entity: &Entity,
+        position: &Position,
+        fishing: &mut Fishing,
+        inventory: &mut Inventory,
+        skills: &Skills,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] skill_experience_queue: &mut SkillExperienceQueue,
+        #[resource] rng: &mut Rng,
+        #[resource] zone: &Zone,
+        #[resource] clock: &WorldClock,
+        #[resource] fishing_info: &FishingInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let pressed = std::mem::take(input);
+        let reel_in = pressed
+            .iter()
+            .any(|input| matches!(input, Input::Move(_) | Input::Back));
+        let strike = pressed
+            .iter()
+            .any(|input| matches!(input, Input::Interact | Input::UseItem));
+
+        if reel_in {
+            commands.remove_component::<Fishing>(*entity);
+            return;
+        }
+
+        fishing.frames_left = fishing.frames_left.saturating_sub(1);
+        let level = skills.level(Skill::Fishing);
+
+        match &mut fishing.phase {
+            FishingPhase::Casting => {
+                if fishing.frames_left == 0 {
+                    fishing.phase = FishingPhase::Waiting;
+                    fishing.frames_left =
+                        rng.range(BITE_FRAMES.0 as i32, BITE_FRAMES.1 as i32) as u32;
+                }
+            }
+            FishingPhase::Waiting => {
+                // Striking before anything's biting just reels the line back in
+                if strike {
+                    commands.remove_component::<Fishing>(*entity);
+                    return;
+                }
+                if fishing.frames_left > 0 {
+                    return;
+                }
+
+                let time = clock.time_of_day();
+                match fishing_info.bite(zone.map.key(), time, level, rng) {
+                    Some(catch) => {
+                        fishing.phase = hook(catch, level, rng);
+                        fishing.frames_left = HOOK_FRAMES;
+                    }
+                    None => commands.remove_component::<Fishing>(*entity),
+                }
+            }
+            FishingPhase::Hooked {
+                item,
+                experience,
+                marker,
+                speed,
+                target,
+            } => {
+                if strike {
+                    if (target.0..=target.1).contains(marker) {
+                        let stack_size = item_info.get(item).map_or(1, |def| def.stack_size);
+                        if inventory.add(item, 1, stack_size) > 0 {
+                            inventory::spawn_ground_item(
+                                commands,
+                                item,
+                                1,
+                                (position.x, position.y),
+                                item_info,
+                            );
+                        }
+                        skill_experience_queue.push(SkillExperienceEvent {
+                            target: *entity,
+                            skill: Skill::Fishing,
+                            amount: *experience,
+                        });
+                    }
+
+                    commands.remove_component::<Fishing>(*entity);
+                    return;
+                }
+
+                if fishing.frames_left == 0 {
+                    // It got away
+                    commands.remove_component::<Fishing>(*entity);
+                    return;
+                }
+
+                // The marker bounces back and forth between the ends of the bar
+                *marker += *speed;
+                if *marker <= 0 || *marker >= BAR_WIDTH {
+                    *marker = (*marker).clamp(0, BAR_WIDTH);
+                    *speed = -*speed;
+                }
+            }
+        }
+    }
+
+    /// Draws the bobber of each player's line, and the catching bar once something's hooked
+    #[system(for_each)]
+    pub fn draw_fishing(
+        position: &Position,
+        fishing: &Fishing,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] map: &TileMap,
+    ) {
+        if fishing.phase == FishingPhase::Casting {
+            return;
+        }
+
+        let (x, y) = map.tile_center(fishing.spot.0, fishing.spot.1);
+        let bar = match fishing.phase {
+            FishingPhase::Hooked { marker, target, .. } => Some((marker, target)),
+            _ => None,
+        };
+
+        render_queue.push(RenderRequest::Fishing {
+            bobber: (x, y),
+            player: (position.x, position.y),
+            bar,
+        });
+    }
+}
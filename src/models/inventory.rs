@@ -0,0 +1,370 @@
+use super::components::*;
+use super::skills::{SkillInfo, Unlock};
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{ItemCategory, ItemInfo};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use sdl2::rect::Point;
+
+/// How close a player has to be to an item to pick it up, in pixels
+pub const PICKUP_RADIUS: i32 = 24;
+
+/// Puts `count` of `item` on the ground with its top left corner at (x, y)
+pub fn spawn_ground_item(
+    commands: &mut CommandBuffer,
+    item: &str,
+    count: u32,
+    position: (i32, i32),
+    item_info: &ItemInfo,
+) -> Option<Entity> {
+    let def = item_info.get(item)?;
+    let (x, y) = position;
+
+    Some(commands.push((
+        Position {
+            x,
+            y,
+            velocity: 1,
+            direction: Direction::Down,
+        },
+        StaticSprite {
+            sheet: String::from("items"),
+            index: def.icon,
+        },
+        GroundItem {
+            item: String::from(item),
+            count,
+        },
+    )))
+}
+
+/// Whether the item is money rather than something that goes in an inventory
+pub fn is_currency(item: &str, item_info: &ItemInfo) -> bool {
+    item_info
+        .get(item)
+        .is_some_and(|def| def.category == ItemCategory::Currency)
+}
+
+/// The equipment slot selected on the inventory screen, if the selection
+/// is in the equipment column rather than among the inventory's slots
+fn selected_equipment(inventory: &Inventory) -> Option<EquipmentSlot> {
+    inventory
+        .selected
+        .checked_sub(inventory.slots.len())
+        .and_then(|i| EQUIPMENT_SLOTS.get(i).copied())
+}
+
+/// Equips the item in the given slot of the inventory, putting whatever
+/// was equipped before back in the inventory in its place
+fn equip(
+    inventory: &mut Inventory,
+    equipment: &mut Equipment,
+    slot: usize,
+    equipment_slot: EquipmentSlot,
+    item_info: &ItemInfo,
+) {
+    let stack = match inventory.take(slot, 1) {
+        Some(stack) => stack,
+        None => return,
+    };
+
+    if let Some(previous) = equipment
+        .slot_mut(equipment_slot)
+        .replace(stack.item.clone())
+    {
+        let stack_size = item_info.get(&previous).map_or(1, |def| def.stack_size);
+
+        // Taking one of a stack might not have freed up a slot,
+        // in which case there's nowhere to put what came off
+        if inventory.add(&previous, 1, stack_size) > 0 {
+            println!("No room to take off {}", previous);
+            *equipment.slot_mut(equipment_slot) = Some(previous);
+            let stack_size = item_info.get(&stack.item).map_or(1, |def| def.stack_size);
+            inventory.add(&stack.item, 1, stack_size);
+        }
+    }
+}
+
+/// Uses the selected item. Consumables restore the health and mana in
+/// their stats, inflict their status effect and are used up, equipment
+/// is equipped if the user's class allows it, and selecting an equipped
+/// item takes it off.
+fn use_selected(
+    user: Entity,
+    inventory: &mut Inventory,
+    equipment: &mut Equipment,
+    class: PlayerClass,
+    stats: &mut PlayerStats,
+    status_queue: &mut StatusQueue,
+    item_info: &ItemInfo,
+) {
+    if let Some(equipment_slot) = selected_equipment(inventory) {
+        if let Some(item) = equipment.slot(equipment_slot).clone() {
+            let stack_size = item_info.get(&item).map_or(1, |def| def.stack_size);
+            if inventory.add(&item, 1, stack_size) == 0 {
+                *equipment.slot_mut(equipment_slot) = None;
+            } else {
+                println!("No room to take off {}", item);
+            }
+        }
+        return;
+    }
+
+    let def = match &inventory.slots[inventory.selected] {
+        Some(stack) => match item_info.get(&stack.item) {
+            Some(def) => def,
+            None => return,
+        },
+        None => return,
+    };
+
+    match (def.category, EquipmentSlot::for_category(def.category)) {
+        (ItemCategory::Consumable, _) => {
+            stats.health.restore(def.stats.health);
+            stats.mana.restore(def.stats.mana);
+            if let Some(status) = def.status {
+                status_queue.push(StatusEvent {
+                    target: user,
+                    source: Some(user),
+                    status,
+                });
+            }
+            inventory.take(inventory.selected, 1);
+        }
+        (_, Some(equipment_slot)) => {
+            if !def.usable_by(class) {
+                return println!("{} can't equip {}", class.to_string(), def.name);
+            }
+            equip(
+                inventory,
+                equipment,
+                inventory.selected,
+                equipment_slot,
+                item_info,
+            );
+        }
+        _ => println!("{} can't be used", def.name),
+    }
+}
+
+/// Moves the selection around the inventory screen's grid, stopping at
+/// the edges rather than wrapping. The equipment column sits to the left
+/// of the grid, level with its first rows.
+fn navigate(inventory: &mut Inventory, direction: Direction) {
+    if selected_equipment(inventory).is_some() {
+        let row = inventory.selected - inventory.slots.len();
+        inventory.selected = match direction {
+            Direction::Up => inventory.slots.len() + row.saturating_sub(1),
+            Direction::Down => inventory.slots.len() + (row + 1).min(EQUIPMENT_SLOTS.len() - 1),
+            Direction::Right => (row * INVENTORY_COLUMNS).min(inventory.slots.len() - 1),
+            Direction::Left => inventory.slots.len() + row,
+        };
+        return;
+    }
+
+    let (column, row) = (
+        inventory.selected % INVENTORY_COLUMNS,
+        inventory.selected / INVENTORY_COLUMNS,
+    );
+    let rows = inventory.slots.len().div_ceil(INVENTORY_COLUMNS);
+
+    if direction == Direction::Left && column == 0 && row < EQUIPMENT_SLOTS.len() {
+        inventory.selected = inventory.slots.len() + row;
+        return;
+    }
+
+    let (column, row) = match direction {
+        Direction::Left => (column.saturating_sub(1), row),
+        Direction::Right => ((column + 1).min(INVENTORY_COLUMNS - 1), row),
+        Direction::Up => (column, row.saturating_sub(1)),
+        Direction::Down => (column, (row + 1).min(rows - 1)),
+    };
+
+    inventory.selected = (row * INVENTORY_COLUMNS + column).min(inventory.slots.len() - 1);
+}
+
+/// Legion systems for carrying, using and dropping items
+pub mod systems {
+    use super::*;
+
+    /// Opens and closes the inventory screen. While it's open it takes
+    /// all of the player's input, so the player stands still while
+    /// items are selected, used, equipped and dropped. Items that a skill
+    /// unlocks can't be used or equipped until the skill's high enough.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn manage_inventory(
+        entity: &Entity,
+        _: &IsPlayerCharacter,
+        class: &PlayerClass,
+        position: &Position,
+        inventory: &mut Inventory,
+        equipment: &mut Equipment,
+        stats: &mut PlayerStats,
+        skills: &Skills,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] status_queue: &mut StatusQueue,
+        #[resource] item_info: &ItemInfo,
+        #[resource] skill_info: &SkillInfo,
+    ) {
+        if input.contains(&Input::ToggleInventory) {
+            inventory.open = !inventory.open;
+            input.retain(|input| *input != Input::ToggleInventory);
+        }
+
+        if !inventory.open {
+            return;
+        }
+
+        for input in input.drain(..) {
+            match input {
+                Input::Navigate(direction) => navigate(inventory, direction),
+                Input::UseItem => {
+                    // Items gated behind a skill can't be used or equipped until it's high enough
+                    let refusal = inventory
+                        .slots
+                        .get(inventory.selected)
+                        .and_then(|stack| stack.as_ref())
+                        .and_then(|stack| {
+                            skill_info.refusal(skills, &Unlock::Item(stack.item.clone()))
+                        });
+                    if let Some(refusal) = refusal {
+                        println!("{}", refusal);
+                        continue;
+                    }
+
+                    use_selected(
+                        *entity,
+                        inventory,
+                        equipment,
+                        *class,
+                        stats,
+                        status_queue,
+                        item_info,
+                    )
+                }
+                Input::DropItem => {
+                    if let Some(stack) = inventory.take(inventory.selected, u32::MAX) {
+                        spawn_ground_item(
+                            commands,
+                            &stack.item,
+                            stack.count,
+                            (position.x, position.y),
+                            item_info,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Picks up the nearest item within reach of each player asking to.
+    /// Whatever doesn't fit in the player's inventory stays on the ground.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[write_component(Inventory)]
+    #[write_component(GroundItem)]
+    pub fn pick_up_items(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        if !input.contains(&Input::PickUp) {
+            return;
+        }
+        input.retain(|input| *input != Input::PickUp);
+
+        let players: Vec<(Entity, Point)> =
+            <(Entity, &Position, &Hurtbox, &Inventory, &IsPlayerCharacter)>::query()
+                .iter(world)
+                .map(|(entity, position, hurtbox, _, _)| (*entity, hurtbox.at(position).center()))
+                .collect();
+
+        for (player, center) in players {
+            let distance = |position: &Position| {
+                let (dx, dy) = (position.x + 8 - center.x(), position.y + 8 - center.y());
+                dx * dx + dy * dy
+            };
+
+            // Gold is collected just by walking over it
+            let nearest = <(Entity, &Position, &GroundItem)>::query()
+                .iter(world)
+                .filter(|(_, _, ground_item)| !is_currency(&ground_item.item, item_info))
+                .filter(|(_, position, _)| distance(position) <= PICKUP_RADIUS * PICKUP_RADIUS)
+                .min_by_key(|(_, position, _)| distance(position))
+                .map(|(entity, _, ground_item)| (*entity, ground_item.clone()));
+
+            let (entity, ground_item) = match nearest {
+                Some(nearest) => nearest,
+                None => continue,
+            };
+
+            let stack_size = item_info
+                .get(&ground_item.item)
+                .map_or(1, |def| def.stack_size);
+            let left = match world.entry_mut(player) {
+                Ok(mut entry) => match entry.get_component_mut::<Inventory>() {
+                    Ok(inventory) => {
+                        inventory.add(&ground_item.item, ground_item.count, stack_size)
+                    }
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+
+            if left == 0 {
+                commands.remove(entity);
+            } else if let Ok(mut entry) = world.entry_mut(entity) {
+                if let Ok(ground_item) = entry.get_component_mut::<GroundItem>() {
+                    ground_item.count = left;
+                }
+            }
+        }
+    }
+
+    #[system(for_each)]
+    pub fn draw_inventory(
+        _: &IsPlayerCharacter,
+        inventory: &Inventory,
+        equipment: &Equipment,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        if !inventory.open {
+            return;
+        }
+
+        let slots = inventory
+            .slots
+            .iter()
+            .map(|slot| {
+                slot.as_ref().and_then(|stack| {
+                    item_info
+                        .get(&stack.item)
+                        .map(|def| (def.icon, stack.count))
+                })
+            })
+            .collect();
+
+        let mut equipped = [None; EQUIPMENT_SLOTS.len()];
+        for (icon, slot) in equipped.iter_mut().zip(EQUIPMENT_SLOTS.iter()) {
+            *icon = equipment
+                .slot(*slot)
+                .as_ref()
+                .and_then(|item| item_info.get(item))
+                .map(|def| def.icon);
+        }
+
+        render_queue.push(RenderRequest::Inventory {
+            slots,
+            equipment: equipped,
+            selected: inventory.selected,
+        });
+    }
+}
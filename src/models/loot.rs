@@ -0,0 +1,239 @@
+use super::components::*;
+use super::inventory;
+use super::zone::{Zone, ZoneMap};
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{ItemInfo, LootInfo, Rng};
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The id of the item that gold drops as
+pub const GOLD_ITEM: &str = "gold";
+/// How long loot lies on the ground before it disappears
+pub const DESPAWN_FRAMES: u32 = 600;
+/// How far loot is scattered from where it drops, in pixels
+const SCATTER: i32 = 10;
+/// How close a player has to be to a chest to open it, in pixels
+pub const CHEST_REACH: i32 = 24;
+
+/// Where the chests that have been opened in each tiled zone are, keyed by
+/// [ZoneMap::key], so they're still open when players come back. Dungeons
+/// are generated afresh each time, so their chests are never remembered.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct OpenedChests(pub HashMap<String, HashSet<(i32, i32)>>);
+
+impl OpenedChests {
+    pub fn open(&mut self, zone: &ZoneMap, position: (i32, i32)) {
+        if let ZoneMap::Tiled(_) = zone {
+            self.0
+                .entry(String::from(zone.key()))
+                .or_default()
+                .insert(position);
+        }
+    }
+
+    pub fn is_open(&self, zone: &ZoneMap, position: (i32, i32)) -> bool {
+        self.0
+            .get(zone.key())
+            .is_some_and(|opened| opened.contains(&position))
+    }
+}
+
+/// Opens the chests of a freshly populated zone that were opened before
+pub fn restore_chests(world: &mut World, opened_chests: &OpenedChests, zone: &ZoneMap) {
+    for (position, chest, sprite) in
+        <(&Position, &mut Chest, &mut StaticSprite)>::query().iter_mut(world)
+    {
+        if !chest.opened && opened_chests.is_open(zone, (position.x, position.y)) {
+            chest.opened = true;
+            sprite.index += 1;
+        }
+    }
+}
+
+/// Rolls the named loot table and scatters what it drops, gold included,
+/// around (x, y). Loot disappears if it isn't picked up in time.
+pub fn drop_loot(
+    commands: &mut CommandBuffer,
+    table: &str,
+    position: (i32, i32),
+    loot_info: &LootInfo,
+    item_info: &ItemInfo,
+    rng: &mut Rng,
+) {
+    let table = match loot_info.get(table) {
+        Some(table) => table,
+        None => return println!("No loot table named {}", table),
+    };
+
+    let mut drops = table.roll(rng);
+    let gold = table.roll_gold(rng);
+    if gold > 0 {
+        drops.push((String::from(GOLD_ITEM), gold));
+    }
+
+    let (x, y) = position;
+    for (item, count) in drops {
+        let scattered = (
+            x + rng.range(-SCATTER, SCATTER + 1),
+            y + rng.range(-SCATTER, SCATTER + 1),
+        );
+
+        if let Some(entity) =
+            inventory::spawn_ground_item(commands, &item, count, scattered, item_info)
+        {
+            commands.add_component(entity, Despawn(DESPAWN_FRAMES));
+        }
+    }
+}
+
+/// Legion systems for loot
+pub mod systems {
+    use super::*;
+
+    /// Drops loot where anything with a loot table died this frame
+    #[system]
+    #[read_component(Position)]
+    #[read_component(LootTable)]
+    pub fn drop_loot_on_death(
+        world: &SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] death_queue: &DeathQueue,
+        #[resource] loot_info: &LootInfo,
+        #[resource] item_info: &ItemInfo,
+        #[resource] rng: &mut Rng,
+    ) {
+        for death in death_queue.iter() {
+            let loot = world.entry_ref(death.entity).ok().and_then(|entry| {
+                let position = entry.get_component::<Position>().ok()?;
+                let table = entry.get_component::<LootTable>().ok()?;
+                Some(((position.x, position.y), table.0.clone()))
+            });
+
+            if let Some((position, table)) = loot {
+                drop_loot(commands, &table, position, loot_info, item_info, rng);
+            }
+        }
+    }
+
+    /// Opens the nearest unopened chest within reach of a player asking
+    /// to pick something up, dropping its loot in front of it. The request
+    /// is used up by the chest, so nothing is picked up the same frame.
+    /// The chest stays open if players leave and come back.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Hurtbox)]
+    #[read_component(Position)]
+    #[read_component(LootTable)]
+    #[write_component(Chest)]
+    #[write_component(StaticSprite)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn open_chests(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] loot_info: &LootInfo,
+        #[resource] item_info: &ItemInfo,
+        #[resource] rng: &mut Rng,
+        #[resource] zone: &Zone,
+        #[resource] opened_chests: &mut OpenedChests,
+    ) {
+        if !input.contains(&Input::PickUp) {
+            return;
+        }
+
+        let players: Vec<sdl2::rect::Point> = <(&Position, &Hurtbox, &IsPlayerCharacter)>::query()
+            .iter(world)
+            .map(|(position, hurtbox, _)| hurtbox.at(position).center())
+            .collect();
+
+        for center in players {
+            let distance = |position: &Position| {
+                let (dx, dy) = (position.x + 8 - center.x(), position.y + 8 - center.y());
+                dx * dx + dy * dy
+            };
+
+            let nearest = <(&Position, &LootTable, &mut Chest, &mut StaticSprite)>::query()
+                .iter_mut(world)
+                .filter(|(position, _, chest, _)| {
+                    !chest.opened && distance(position) <= CHEST_REACH * CHEST_REACH
+                })
+                .min_by_key(|(position, _, _, _)| distance(position));
+
+            if let Some((position, table, chest, sprite)) = nearest {
+                chest.opened = true;
+                opened_chests.open(&zone.map, (position.x, position.y));
+                // Open chests are drawn from the sprite after the closed one
+                sprite.index += 1;
+
+                let front = (position.x, position.y + 16);
+                drop_loot(commands, &table.0, front, loot_info, item_info, rng);
+                input.retain(|input| *input != Input::PickUp);
+            }
+        }
+    }
+
+    /// Adds any gold lying within reach of a player to their purse
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Hurtbox)]
+    #[read_component(Position)]
+    #[read_component(GroundItem)]
+    #[write_component(Gold)]
+    pub fn collect_gold(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let coins: Vec<(Entity, Position, u32)> = <(Entity, &Position, &GroundItem)>::query()
+            .iter(world)
+            .filter(|(_, _, ground_item)| inventory::is_currency(&ground_item.item, item_info))
+            .map(|(entity, position, ground_item)| (*entity, *position, ground_item.count))
+            .collect();
+
+        if coins.is_empty() {
+            return;
+        }
+
+        let mut collected = vec![];
+        for (center, gold) in <(&Position, &Hurtbox, &mut Gold, &IsPlayerCharacter)>::query()
+            .iter_mut(world)
+            .map(|(position, hurtbox, gold, _)| (hurtbox.at(position).center(), gold))
+        {
+            for (entity, position, count) in coins.iter() {
+                let (dx, dy) = (position.x + 8 - center.x(), position.y + 8 - center.y());
+                let reach = inventory::PICKUP_RADIUS;
+
+                if dx * dx + dy * dy <= reach * reach && !collected.contains(entity) {
+                    gold.0 += count;
+                    collected.push(*entity);
+                    commands.remove(*entity);
+                }
+            }
+        }
+    }
+
+    /// Puts how much gold each player has under their status icons
+    #[system(for_each)]
+    pub fn draw_gold(
+        _: &IsPlayerCharacter,
+        gold: &Gold,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        if let Some(def) = item_info.get(GOLD_ITEM) {
+            render_queue.push(RenderRequest::Gold(def.icon, gold.0));
+        }
+    }
+
+    /// Counts down entities that disappear after a while, removing them once they're up
+    #[system(for_each)]
+    pub fn despawn(entity: &Entity, despawn: &mut Despawn, commands: &mut CommandBuffer) {
+        despawn.0 = despawn.0.saturating_sub(1);
+        if despawn.0 == 0 {
+            commands.remove(*entity);
+        }
+    }
+}
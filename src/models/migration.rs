@@ -0,0 +1,160 @@
+use super::clock::WorldClock;
+use super::components::*;
+use super::farming::Farms;
+use super::loot::OpenedChests;
+use super::save::{self, SaveFile, SAVE_VERSION};
+use super::shop::Shops;
+use super::zone::ZoneMap;
+use serde::Deserialize;
+
+/// Just enough of a save to tell which version wrote it
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// The formats earlier versions saved in. Only the parts that have
+/// changed since are frozen here; the rest are read as they are now.
+pub mod v1 {
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct PlayerSave {
+        pub class: PlayerClass,
+        pub gender: Gender,
+        pub position: Position,
+        pub stats: PlayerStats,
+        pub inventory: Inventory,
+        pub gold: Gold,
+        pub equipment: Equipment,
+        pub quest_log: QuestLog,
+        pub skills: Skills,
+    }
+
+    /// The version's left out, having already been read from the header
+    #[derive(Deserialize)]
+    pub struct SaveFile {
+        pub zone: ZoneMap,
+        pub player: PlayerSave,
+        pub clock: WorldClock,
+        pub farms: Farms,
+        pub opened_chests: OpenedChests,
+        pub shops: Shops,
+    }
+}
+
+/// Version 2 keeps where the player respawns. Version 1 didn't,
+/// so players from it respawn wherever they were saved.
+fn upgrade_v1(save: v1::SaveFile) -> SaveFile {
+    let player = save.player;
+
+    SaveFile {
+        version: 2,
+        zone: save.zone,
+        player: save::PlayerSave {
+            class: player.class,
+            gender: player.gender,
+            position: player.position,
+            spawn: SpawnPoint {
+                x: player.position.x,
+                y: player.position.y,
+            },
+            stats: player.stats,
+            inventory: player.inventory,
+            gold: player.gold,
+            equipment: player.equipment,
+            quest_log: player.quest_log,
+            skills: player.skills,
+        },
+        clock: save.clock,
+        farms: save.farms,
+        opened_chests: save.opened_chests,
+        shops: save.shops,
+    }
+}
+
+/// Reads a save written by this version or any before it. Older saves are
+/// read in the format they were written in, then upgraded a version at a
+/// time until they're current. When the format next changes, the current
+/// [SaveFile] is frozen into a `v2` module, an `upgrade_v2` step is added
+/// and the steps before it are chained through it.
+pub fn read(contents: &str) -> Result<SaveFile, Box<dyn std::error::Error>> {
+    let SaveHeader { version } = ron::from_str(contents)?;
+
+    let save = match version {
+        1 => upgrade_v1(ron::from_str(contents)?),
+        SAVE_VERSION => ron::from_str(contents)?,
+        _ => {
+            return Err(format!(
+                "save is from version {}, which version {} can't read",
+                version, SAVE_VERSION
+            )
+            .into())
+        }
+    };
+
+    Ok(save)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = include_str!("../../tests/fixtures/saves/v1.ron");
+    const V2: &str = include_str!("../../tests/fixtures/saves/v2.ron");
+
+    #[test]
+    fn upgrades_v1_saves() {
+        let save = read(V1).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.zone, ZoneMap::Tiled(String::from("Assets/map.tmx")));
+        assert_eq!(save.player.class, PlayerClass::Archer);
+        assert_eq!(save.player.stats.level, 3);
+        assert_eq!(save.player.gold, Gold(42));
+        assert_eq!(save.player.inventory.count("turnip_seeds"), 5);
+        assert_eq!(save.player.skills.level(Skill::Fishing), 2);
+        assert_eq!(save.clock.day(), 1);
+        assert!(save.opened_chests.is_open(&save.zone, (224, 288)));
+    }
+
+    #[test]
+    fn v1_players_respawn_where_they_were_saved() {
+        let save = read(V1).unwrap();
+        let position = save.player.position;
+
+        assert_eq!(
+            save.player.spawn,
+            SpawnPoint {
+                x: position.x,
+                y: position.y
+            }
+        );
+    }
+
+    #[test]
+    fn reads_current_saves_as_they_are() {
+        let save = read(V2).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.zone, ZoneMap::Dungeon(1234));
+        assert_eq!(save.player.spawn, SpawnPoint { x: 488, y: 96 });
+        assert_eq!(save.player.equipment.weapon.as_deref(), Some("iron_sword"));
+        assert_eq!(save.farms.zones["Assets/map.tmx"].crops.len(), 1);
+    }
+
+    #[test]
+    fn current_saves_round_trip() {
+        let save = read(V2).unwrap();
+        let written = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default()).unwrap();
+
+        assert_eq!(read(&written).unwrap(), save);
+    }
+
+    #[test]
+    fn rejects_saves_from_newer_versions() {
+        let newer = V2.replacen("version: 2", "version: 99", 1);
+
+        assert!(read(&newer).is_err());
+    }
+}
@@ -1,2 +1,25 @@
+pub mod abilities;
+pub mod clock;
+pub mod combat;
 pub mod components;
+pub mod crafting;
+pub mod dialogue;
+pub mod enemy;
+pub mod farming;
+pub mod fishing;
+pub mod inventory;
+pub mod loot;
+pub mod migration;
+pub mod npc;
 pub mod player;
+pub mod prefab;
+pub mod progression;
+pub mod projectile;
+pub mod quest;
+pub mod save;
+pub mod shop;
+pub mod skills;
+pub mod snapshot;
+pub mod stats;
+pub mod status;
+pub mod zone;
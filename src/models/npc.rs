@@ -0,0 +1,125 @@
+use super::clock::WorldClock;
+use super::components::*;
+use super::enemy::step_towards;
+use crate::map::pathfinding::find_path;
+use crate::map::TileMap;
+use legion::world::SubWorld;
+use legion::*;
+use sdl2::rect::Point;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where an NPC goes from a given hour
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduleEntry {
+    /// The hour of the day, from 0 to 23, the NPC sets off at
+    pub from: u32,
+    /// The name of the `waypoint` object the NPC heads to
+    pub place: String,
+}
+
+/// Every NPC schedule, by name
+#[derive(Deserialize, Serialize)]
+pub struct ScheduleInfo {
+    pub schedules: HashMap<String, Vec<ScheduleEntry>>,
+}
+
+impl ScheduleInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let schedule_info = ron::from_str(&contents)?;
+        Ok(schedule_info)
+    }
+
+    /// Where an NPC keeping to the named schedule should be at the given
+    /// hour. Before the first entry of the day, the NPC is still wherever
+    /// the last entry of the day before sent it.
+    pub fn place_at(&self, schedule: &str, hour: u32) -> Option<&str> {
+        let entries = self.schedules.get(schedule)?;
+
+        entries
+            .iter()
+            .filter(|entry| entry.from <= hour)
+            .max_by_key(|entry| entry.from)
+            .or_else(|| entries.iter().max_by_key(|entry| entry.from))
+            .map(|entry| entry.place.as_str())
+    }
+}
+
+/// Legion systems for NPCs going about their day
+pub mod systems {
+    use super::*;
+
+    /// Walks each NPC with a schedule to wherever it should be at this
+    /// hour, which is the tiled object of kind `waypoint` with that name.
+    /// NPCs stop to talk, and stand still once they get there.
+    #[system]
+    #[read_component(Conversation)]
+    #[write_component(Position)]
+    #[write_component(NpcSchedule)]
+    #[write_component(PlayerState)]
+    pub fn follow_schedules(
+        world: &mut SubWorld,
+        #[resource] clock: &WorldClock,
+        #[resource] map: &TileMap,
+        #[resource] schedule_info: &ScheduleInfo,
+    ) {
+        let talking: Vec<Entity> = <&Conversation>::query()
+            .iter(world)
+            .map(|conversation| conversation.npc)
+            .collect();
+
+        for (entity, position, npc_schedule, state) in
+            <(Entity, &mut Position, &mut NpcSchedule, &mut PlayerState)>::query().iter_mut(world)
+        {
+            if talking.contains(entity) {
+                *state = PlayerState::Idle;
+                continue;
+            }
+
+            let place = match schedule_info.place_at(&npc_schedule.schedule, clock.hour()) {
+                Some(place) => place,
+                None => continue,
+            };
+            let center = Point::new(position.x + 8, position.y + 8);
+
+            if npc_schedule.place.as_deref() != Some(place) {
+                npc_schedule.place = Some(String::from(place));
+                npc_schedule.path = match map
+                    .objects_of_kind("waypoint")
+                    .find(|waypoint| waypoint.name == place)
+                {
+                    Some(waypoint) => {
+                        let start = map.tile_at(center.x(), center.y());
+                        let goal = map.tile_at(
+                            waypoint.x + waypoint.width as i32 / 2,
+                            waypoint.y + waypoint.height as i32 / 2,
+                        );
+                        find_path(map, start, goal).unwrap_or_default()
+                    }
+                    None => {
+                        println!("No waypoint named {}", place);
+                        vec![]
+                    }
+                };
+            }
+
+            let speed = position.velocity;
+            while let Some(&(x, y)) = npc_schedule.path.first() {
+                let (waypoint_x, waypoint_y) = map.tile_center(x, y);
+                let waypoint = Point::new(waypoint_x, waypoint_y);
+                if step_towards(position, center, waypoint, speed) {
+                    npc_schedule.path.remove(0);
+                } else {
+                    break;
+                }
+            }
+
+            *state = if npc_schedule.path.is_empty() {
+                PlayerState::Idle
+            } else {
+                PlayerState::Moving
+            };
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use super::components::*;
+use super::status;
+use crate::map::TileMap;
 use crate::rendering::{RenderQueue, RenderRequest, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::util::StatusInfo;
 use legion::*;
 
 /// Produces a new player and pushes it to the world
@@ -20,7 +23,51 @@ pub fn new(
     let state = PlayerState::Idle;
     let stats = PlayerStats::default();
 
-    world.push((IsPlayerCharacter, class, gender, stats, position, state))
+    let player = world.push((IsPlayerCharacter, class, gender, stats, position, state));
+
+    // Legion only takes tuples of up to eight components at once,
+    // so the combat components are added separately
+    if let Some(mut entry) = world.entry(player) {
+        entry.add_component(Global);
+        entry.add_component(SpawnPoint { x, y });
+        entry.add_component(Invulnerability(0));
+        entry.add_component(StateTimer(0));
+        entry.add_component(MeleeState {
+            frame: 0,
+            cooldown: 0,
+        });
+        entry.add_component(Abilities::default());
+        entry.add_component(LevelUpNotice(0));
+        entry.add_component(Faction::Players);
+        entry.add_component(Inventory::default());
+        entry.add_component(Gold::default());
+        entry.add_component(QuestLog::default());
+        entry.add_component(Skills::default());
+        entry.add_component(Equipment::default());
+        entry.add_component(Buffs::default());
+        entry.add_component(EffectiveStats::default());
+        entry.add_component(StatusEffects::default());
+        entry.add_component(Hurtbox {
+            x_offset: 2,
+            y_offset: 8,
+            width: 12,
+            height: 20,
+        });
+    }
+
+    player
+}
+
+/// The tile in front of whoever's at `position`, in tile coordinates
+pub fn facing_tile(position: &Position, hurtbox: &Hurtbox, map: &TileMap) -> (i32, i32) {
+    let center = hurtbox.at(position).center();
+    let (dx, dy) = position.direction.as_vector();
+    let (tile_width, tile_height) = map.tile_dimensions;
+
+    map.tile_at(
+        center.x() + dx * tile_width as i32,
+        center.y() + dy * tile_height as i32,
+    )
 }
 
 /// Attempts to move a player to (x, y) but will fail if the move is illegal.
@@ -54,8 +101,13 @@ fn move_to(position: &mut Position, state: &mut PlayerState, x: i32, y: i32) {
 /// A utility function for handling movement input.
 /// This is just a messy match that I didn't want
 /// cluttering the input system.
-fn handle_movement_input(direction: &Direction, position: &mut Position, state: &mut PlayerState) {
-    let move_by = position.velocity * 4;
+fn handle_movement_input(
+    direction: &Direction,
+    position: &mut Position,
+    state: &mut PlayerState,
+    speed_percent: u32,
+) {
+    let move_by = (position.velocity * 4 * speed_percent as i32 / 100).max(1);
 
     match direction {
         Direction::Up => {
@@ -110,29 +162,45 @@ pub mod systems {
     }
 
     #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
     pub fn player_input(
         _: &IsPlayerCharacter,
         position: &mut Position,
         state: &mut PlayerState,
+        timer: &StateTimer,
+        abilities: &mut Abilities,
+        effects: Option<&StatusEffects>,
         #[resource] input: &mut Vec<Input>,
+        #[resource] status_info: &StatusInfo,
     ) {
-        if input.len() == 0 {
-            *state = PlayerState::Idle;
-            position.velocity = 1;
-        } else {
-            while let Some(input) = input.pop() {
-                match input {
-                    Input::Move(direction) => {
-                        handle_movement_input(&direction, position, state);
-                    }
-                    Input::Run => {
-                        position.velocity = 2;
-                    }
-                    Input::Attack => {
-                        *state = PlayerState::Attack;
-                    }
-                    _ => {}
+        // Players can't act while flinching or dead
+        if timer.0 > 0 {
+            input.clear();
+            return;
+        }
+
+        let speed_percent =
+            effects.map_or(100, |effects| status::speed_percent(effects, status_info));
+
+        // Stand still unless there's input saying otherwise
+        *state = PlayerState::Idle;
+        position.velocity = 1;
+
+        while let Some(input) = input.pop() {
+            match input {
+                Input::Move(direction) => {
+                    handle_movement_input(&direction, position, state, speed_percent);
+                }
+                Input::Run => {
+                    position.velocity = 2;
+                }
+                Input::Attack => {
+                    *state = PlayerState::Attack;
+                }
+                Input::Ability(slot) => {
+                    abilities.queued = Some(slot);
                 }
+                _ => {}
             }
         }
     }
@@ -0,0 +1,277 @@
+use super::components::*;
+use crate::map::{MapObject, TileMap};
+use crate::rendering::{RenderQueue, RenderRequest};
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PrefabSprite {
+    /// Animated sprites with a variant for each direction and state
+    Directional(String),
+    /// A single sprite from a sheet
+    Static(StaticSprite),
+}
+
+/// A description of an entity that any number of copies can be spawned
+/// from. Everything besides the sprite is optional, and which parts are
+/// present decides what the entity is: enemies have `stats` and `ai`,
+/// NPCs set `npc`, items lying on the ground have an `item`, and chests
+/// set `chest` and roll their `loot_table` when opened.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Prefab {
+    pub sprite: PrefabSprite,
+    #[serde(default)]
+    pub faction: Option<Faction>,
+    #[serde(default)]
+    pub hurtbox: Option<Hurtbox>,
+    #[serde(default)]
+    pub stats: Option<EnemyStats>,
+    #[serde(default)]
+    pub ai: Option<AiProfile>,
+    /// Experience awarded to whoever kills the entity
+    #[serde(default)]
+    pub experience: Option<u32>,
+    #[serde(default)]
+    pub loot_table: Option<String>,
+    #[serde(default)]
+    pub chest: bool,
+    #[serde(default)]
+    pub npc: bool,
+    /// The conversation an NPC starts when talked to
+    #[serde(default)]
+    pub dialogue: Option<String>,
+    /// The schedule an NPC keeps to, if it moves about during the day
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub item: Option<GroundItem>,
+}
+
+impl Prefab {
+    /// Produces a copy of the prefab with the properties of a tiled object
+    /// laid over the top of it. The int properties `health`, `damage`,
+    /// `speed`, `aggro_radius`, `leash_radius`, `wander_radius`, `experience`
+    /// and `count`, and the string properties `sprite`, `loot_table`,
+    /// `dialogue`, `schedule` and `item` each replace the matching part of the prefab.
+    pub fn with_overrides(&self, object: &MapObject) -> Prefab {
+        let mut prefab = self.clone();
+
+        if let Some(sprite) = object.string_property("sprite") {
+            prefab.sprite = match prefab.sprite {
+                PrefabSprite::Directional(_) => PrefabSprite::Directional(String::from(sprite)),
+                PrefabSprite::Static(static_sprite) => PrefabSprite::Static(StaticSprite {
+                    sheet: String::from(sprite),
+                    ..static_sprite
+                }),
+            };
+        }
+
+        if let Some(stats) = prefab.stats.as_mut() {
+            if let Some(health) = object.int_property("health") {
+                stats.health = PrimaryStat {
+                    current: health as u32,
+                    max: health as u32,
+                };
+            }
+            if let Some(damage) = object.int_property("damage") {
+                stats.damage = damage as u32;
+            }
+            if let Some(speed) = object.int_property("speed") {
+                stats.speed = speed;
+            }
+        }
+
+        if let Some(ai) = prefab.ai.as_mut() {
+            if let Some(radius) = object.int_property("aggro_radius") {
+                ai.aggro_radius = radius as u32;
+            }
+            if let Some(radius) = object.int_property("leash_radius") {
+                ai.leash_radius = radius as u32;
+            }
+            if let Some(radius) = object.int_property("wander_radius") {
+                ai.wander_radius = radius as u32;
+            }
+        }
+
+        if let Some(item) = prefab.item.as_mut() {
+            if let Some(name) = object.string_property("item") {
+                item.item = String::from(name);
+            }
+            if let Some(count) = object.int_property("count") {
+                item.count = count as u32;
+            }
+        }
+
+        if let Some(experience) = object.int_property("experience") {
+            prefab.experience = Some(experience as u32);
+        }
+        if let Some(loot_table) = object.string_property("loot_table") {
+            prefab.loot_table = Some(String::from(loot_table));
+        }
+        if let Some(dialogue) = object.string_property("dialogue") {
+            prefab.dialogue = Some(String::from(dialogue));
+        }
+        if let Some(schedule) = object.string_property("schedule") {
+            prefab.schedule = Some(String::from(schedule));
+        }
+
+        prefab
+    }
+
+    /// Pushes a new entity built from the prefab to the world at the
+    /// given position. `name` is recorded on the entity as its [PrefabName].
+    pub fn spawn(&self, world: &mut World, name: &str, position: (i32, i32)) -> Entity {
+        let (x, y) = position;
+        let entity = world.push((
+            PrefabName(String::from(name)),
+            Position {
+                x,
+                y,
+                velocity: 1,
+                direction: Direction::Down,
+            },
+            SpawnPoint { x, y },
+        ));
+
+        // Legion can't push a tuple with optional parts,
+        // so everything else is added one component at a time
+        let mut entry = world.entry(entity).unwrap();
+
+        match &self.sprite {
+            PrefabSprite::Directional(sprite) => {
+                entry.add_component(DirectionalSprite(sprite.clone()));
+                entry.add_component(PlayerState::Idle);
+            }
+            PrefabSprite::Static(sprite) => {
+                entry.add_component(sprite.clone());
+            }
+        }
+
+        if let Some(faction) = self.faction {
+            entry.add_component(faction);
+        }
+        if let Some(hurtbox) = self.hurtbox {
+            entry.add_component(hurtbox);
+        }
+        if let Some(stats) = self.stats {
+            entry.add_component(stats);
+            entry.add_component(StateTimer(0));
+            entry.add_component(StatusEffects::default());
+        }
+        if let Some(ai) = self.ai {
+            entry.add_component(Ai::new(ai));
+        }
+        if let Some(experience) = self.experience {
+            entry.add_component(ExperienceReward(experience));
+        }
+        if let Some(loot_table) = &self.loot_table {
+            entry.add_component(LootTable(loot_table.clone()));
+        }
+        if self.chest {
+            entry.add_component(Chest { opened: false });
+        }
+        if self.npc {
+            entry.add_component(Npc {
+                dialogue: self.dialogue.clone(),
+            });
+        }
+        if let Some(schedule) = &self.schedule {
+            entry.add_component(NpcSchedule {
+                schedule: schedule.clone(),
+                place: None,
+                path: vec![],
+            });
+        }
+        if let Some(item) = &self.item {
+            entry.add_component(item.clone());
+        }
+
+        entity
+    }
+}
+
+/// Every prefab, by name
+#[derive(Deserialize, Serialize)]
+pub struct PrefabInfo {
+    pub prefabs: HashMap<String, Prefab>,
+}
+
+impl PrefabInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let prefab_info = ron::from_str(&contents)?;
+        Ok(prefab_info)
+    }
+
+    /// Spawns the prefab with the given name at the given position, with
+    /// the properties of a tiled object as overrides if one's given.
+    /// Returns `None` if there's no such prefab.
+    pub fn spawn(
+        &self,
+        world: &mut World,
+        name: &str,
+        position: (i32, i32),
+        overrides: Option<&MapObject>,
+    ) -> Option<Entity> {
+        let prefab = self.prefabs.get(name)?;
+
+        Some(match overrides {
+            Some(object) => prefab.with_overrides(object).spawn(world, name, position),
+            None => prefab.spawn(world, name, position),
+        })
+    }
+
+    /// Spawns a prefab at every `spawn` object in the map, with the object's
+    /// properties as overrides. The prefab is named by the object's `prefab`
+    /// property, or by the object's name if it doesn't have one.
+    pub fn spawn_from_map(&self, world: &mut World, map: &TileMap) -> Vec<Entity> {
+        let mut entities = vec![];
+
+        for object in map.objects_of_kind("spawn") {
+            let name = object.string_property("prefab").unwrap_or(&object.name);
+
+            match self.spawn(world, name, (object.x, object.y), Some(object)) {
+                Some(entity) => entities.push(entity),
+                None => println!("No prefab named {} to spawn", name),
+            }
+        }
+
+        entities
+    }
+}
+
+/// Legion systems for drawing entities spawned from prefabs
+pub mod systems {
+    use super::*;
+
+    #[system(for_each)]
+    pub fn animate_directional_sprites(
+        sprite: &DirectionalSprite,
+        position: &Position,
+        state: &PlayerState,
+        stats: Option<&EnemyStats>,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        render_queue.push(RenderRequest::Directional {
+            sprite: sprite.0.clone(),
+            position: *position,
+            state: *state,
+            health: stats.map(|stats| stats.health.as_percent()),
+        });
+    }
+
+    #[system(for_each)]
+    pub fn draw_static_sprites(
+        sprite: &StaticSprite,
+        position: &Position,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        render_queue.push(RenderRequest::Static {
+            sheet: sprite.sheet.clone(),
+            index: sprite.index,
+            x: position.x,
+            y: position.y,
+        });
+    }
+}
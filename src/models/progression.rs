@@ -0,0 +1,153 @@
+use super::components::*;
+use super::stats;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{ItemInfo, ProgressionInfo};
+use legion::world::SubWorld;
+use legion::*;
+
+/// How long the level-up notification stays up for
+pub const LEVEL_UP_FRAMES: u32 = 60;
+
+/// Adds experience to `stats`, levelling up as many times as it
+/// takes to bring the experience back under the max. Each level rolls
+/// its excess experience over into the next. Returns whether any
+/// levels were gained. The maximums of health and mana follow the
+/// level, but are left to [stats::update] to work out.
+pub fn gain_experience(
+    stats: &mut PlayerStats,
+    amount: u32,
+    progression_info: &ProgressionInfo,
+) -> bool {
+    let mut levelled_up = false;
+
+    stats.experience.current += amount;
+
+    while stats.experience.current >= stats.experience.max {
+        stats.experience.current -= stats.experience.max;
+        stats.level += 1;
+        stats.experience.max = progression_info
+            .experience_curve
+            .required(stats.level)
+            .max(1);
+
+        levelled_up = true;
+    }
+
+    levelled_up
+}
+
+/// Legion systems for experience and levelling
+pub mod systems {
+    use super::*;
+
+    /// Turns this frame's deaths into experience
+    /// for the killers of anything worth experience
+    #[system]
+    #[read_component(ExperienceReward)]
+    pub fn award_kill_experience(
+        world: &SubWorld,
+        #[resource] death_queue: &DeathQueue,
+        #[resource] experience_queue: &mut ExperienceQueue,
+    ) {
+        for death in death_queue.iter() {
+            let killer = match death.killer {
+                Some(killer) => killer,
+                None => continue,
+            };
+
+            let reward = world
+                .entry_ref(death.entity)
+                .ok()
+                .and_then(|entry| entry.get_component::<ExperienceReward>().ok().copied());
+
+            if let Some(ExperienceReward(amount)) = reward {
+                experience_queue.push(ExperienceEvent {
+                    target: killer,
+                    amount,
+                });
+            }
+        }
+    }
+
+    /// Drains the experience queue into each target's stats. Targets
+    /// that level up have their health and mana refilled to the new
+    /// level's maximums, and a notification put up.
+    #[system]
+    #[read_component(PlayerClass)]
+    #[read_component(Equipment)]
+    #[read_component(Buffs)]
+    #[write_component(PlayerStats)]
+    #[write_component(EffectiveStats)]
+    #[write_component(LevelUpNotice)]
+    pub fn apply_experience(
+        world: &mut SubWorld,
+        #[resource] experience_queue: &mut ExperienceQueue,
+        #[resource] item_info: &ItemInfo,
+        #[resource] progression_info: &ProgressionInfo,
+    ) {
+        for event in experience_queue.drain(..) {
+            let mut entry = match world.entry_mut(event.target) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            let class = match entry.get_component::<PlayerClass>() {
+                Ok(class) => *class,
+                Err(_) => continue,
+            };
+
+            let equipment = entry
+                .get_component::<Equipment>()
+                .cloned()
+                .unwrap_or_default();
+            let buffs = entry.get_component::<Buffs>().ok().copied();
+
+            let effective = match entry.get_component_mut::<PlayerStats>() {
+                Ok(stats) => {
+                    if !gain_experience(stats, event.amount, progression_info) {
+                        continue;
+                    }
+
+                    let effective = stats::update(
+                        class,
+                        stats,
+                        &equipment,
+                        buffs.as_ref(),
+                        item_info,
+                        progression_info,
+                    );
+                    stats.health.refill();
+                    stats.mana.refill();
+                    effective
+                }
+                Err(_) => continue,
+            };
+
+            if let Ok(current) = entry.get_component_mut::<EffectiveStats>() {
+                *current = effective;
+            }
+            if let Ok(notice) = entry.get_component_mut::<LevelUpNotice>() {
+                notice.0 = LEVEL_UP_FRAMES;
+            }
+        }
+    }
+
+    #[system(for_each)]
+    pub fn draw_level_up(
+        position: &Position,
+        stats: &PlayerStats,
+        notice: &mut LevelUpNotice,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        if notice.0 == 0 {
+            return;
+        }
+
+        notice.0 -= 1;
+        render_queue.push(RenderRequest::LevelUp {
+            x: position.x,
+            y: position.y,
+            level: stats.level,
+        });
+    }
+}
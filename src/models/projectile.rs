@@ -0,0 +1,200 @@
+use super::combat;
+use super::components::*;
+use crate::map::TileMap;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::ProjectileInfo;
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+
+/// The furthest a projectile moves between collision checks, in pixels.
+/// Keeping this below the width of the smallest hurtbox stops fast
+/// projectiles from passing straight through their targets.
+const MAX_STEP: i32 = 4;
+
+/// Spawns a projectile at `origin` travelling along `heading`,
+/// which doesn't need to be normalised.
+pub fn spawn(
+    commands: &mut CommandBuffer,
+    owner: Entity,
+    origin: (i32, i32),
+    heading: (f32, f32),
+    info: &ProjectileInfo,
+) -> Entity {
+    let (heading_x, heading_y) = heading;
+    let length = (heading_x * heading_x + heading_y * heading_y)
+        .sqrt()
+        .max(f32::EPSILON);
+
+    let velocity = Velocity {
+        x: (heading_x / length * info.speed as f32).round() as i32,
+        y: (heading_y / length * info.speed as f32).round() as i32,
+    };
+
+    // Projectiles don't use the positional velocity or direction for
+    // movement, but the direction keeps them consistent with everything else
+    let direction = if heading_x.abs() > heading_y.abs() {
+        if heading_x < 0.0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    } else if heading_y < 0.0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    };
+
+    let position = Position {
+        x: origin.0,
+        y: origin.1,
+        velocity: 0,
+        direction,
+    };
+
+    commands.push((
+        position,
+        velocity,
+        Projectile {
+            owner,
+            info: *info,
+            frames_left: info.lifetime,
+            pierce: info.pierce,
+            struck: vec![],
+        },
+    ))
+}
+
+/// Ends a projectile's flight at (x, y), setting off its explosion if it has one
+fn land(
+    entity: Entity,
+    projectile: &Projectile,
+    x: i32,
+    y: i32,
+    direction: Direction,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(explosion) = projectile.info.explosion {
+        commands.push((Hitbox {
+            owner: projectile.owner,
+            origin: (x, y),
+            direction,
+            reach: explosion.radius,
+            arc: 360.0,
+            damage: explosion.damage,
+            status: projectile.info.status,
+            frames_left: 1,
+            struck: vec![],
+        },));
+    }
+
+    commands.remove(entity);
+}
+
+/// Legion systems that move projectiles and resolve their hits
+pub mod systems {
+    use super::*;
+
+    /// Moves each projectile along its velocity in small steps, damaging
+    /// the hurtboxes it passes through. Projectiles land when they hit a
+    /// solid tile, run out of targets to pierce, or run out of lifetime.
+    #[system]
+    #[read_component(Hurtbox)]
+    #[read_component(Faction)]
+    #[read_component(Velocity)]
+    #[write_component(Position)]
+    #[write_component(Projectile)]
+    pub fn simulate_projectiles(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] map: &TileMap,
+        #[resource] damage_queue: &mut DamageQueue,
+        #[resource] status_queue: &mut StatusQueue,
+    ) {
+        let targets: Vec<(Entity, sdl2::rect::Rect)> = <(Entity, &Position, &Hurtbox)>::query()
+            .iter(world)
+            .map(|(entity, position, hurtbox)| (*entity, hurtbox.at(position)))
+            .collect();
+        let factions = combat::factions(world);
+
+        let mut query = <(Entity, &mut Position, &Velocity, &mut Projectile)>::query();
+        for (entity, position, velocity, projectile) in query.iter_mut(world) {
+            let (start_x, start_y) = (position.x, position.y);
+            let steps = ((velocity.x.abs().max(velocity.y.abs()) + MAX_STEP - 1) / MAX_STEP).max(1);
+            let mut landed = false;
+
+            for step in 1..=steps {
+                position.x = start_x + velocity.x * step / steps;
+                position.y = start_y + velocity.y * step / steps;
+
+                if map.blocks_point(position.x, position.y) {
+                    landed = true;
+                    break;
+                }
+
+                for (target, rect) in targets.iter() {
+                    if !combat::can_hit(&factions, projectile.owner, *target)
+                        || projectile.struck.contains(target)
+                        || !rect.contains_point((position.x, position.y))
+                    {
+                        continue;
+                    }
+
+                    projectile.struck.push(*target);
+                    damage_queue.push(DamageEvent {
+                        target: *target,
+                        source: Some(projectile.owner),
+                        amount: projectile.info.damage,
+                        over_time: false,
+                    });
+                    if let Some(status) = projectile.info.status {
+                        status_queue.push(StatusEvent {
+                            target: *target,
+                            source: Some(projectile.owner),
+                            status,
+                        });
+                    }
+
+                    if projectile.pierce == 0 {
+                        landed = true;
+                        break;
+                    }
+                    projectile.pierce -= 1;
+                }
+
+                if landed {
+                    break;
+                }
+            }
+
+            projectile.frames_left = projectile.frames_left.saturating_sub(1);
+            if landed || projectile.frames_left == 0 {
+                land(
+                    *entity,
+                    projectile,
+                    position.x,
+                    position.y,
+                    position.direction,
+                    commands,
+                );
+            }
+        }
+    }
+
+    #[system(for_each)]
+    pub fn draw_projectiles(
+        position: &Position,
+        velocity: &Velocity,
+        projectile: &Projectile,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        let angle = (velocity.y as f64).atan2(velocity.x as f64).to_degrees();
+
+        render_queue.push(RenderRequest::Projectile {
+            sprite: projectile.info.sprite,
+            x: position.x,
+            y: position.y,
+            angle,
+        });
+    }
+}
@@ -0,0 +1,283 @@
+use super::components::*;
+use super::inventory;
+use super::zone::object_rect;
+use crate::map::TileMap;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::ItemInfo;
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What a player has to do to meet an objective
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ObjectiveKind {
+    /// Kill enemies spawned from the named prefab
+    Kill(String),
+    /// Carry the named item. Collected items are handed
+    /// over when the quest is completed.
+    Collect(String),
+    /// Finish a conversation that follows the named dialogue tree. This
+    /// only counts once every other objective of the quest has been met,
+    /// so it's how a quest is handed in.
+    TalkTo(String),
+    /// Walk into the tiled object of kind `region` with the given name
+    Reach(String),
+}
+
+fn once() -> u32 {
+    1
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Objective {
+    /// What the quest tracker calls the objective
+    pub description: String,
+    pub kind: ObjectiveKind,
+    /// How many kills or items the objective needs
+    #[serde(default = "once")]
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QuestRewards {
+    pub experience: u32,
+    pub gold: u32,
+    pub items: Vec<(String, u32)>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuestDef {
+    /// The name shown in the quest tracker
+    pub name: String,
+    pub objectives: Vec<Objective>,
+    #[serde(default)]
+    pub rewards: QuestRewards,
+}
+
+/// Every quest, by name
+#[derive(Deserialize, Serialize)]
+pub struct QuestInfo {
+    pub quests: HashMap<String, QuestDef>,
+}
+
+impl QuestInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let quest_info = ron::from_str(&contents)?;
+        Ok(quest_info)
+    }
+
+    pub fn get(&self, quest: &str) -> Option<&QuestDef> {
+        self.quests.get(quest)
+    }
+}
+
+/// What a player did this frame that could move their quests along
+struct Deeds<'a> {
+    /// The prefabs of everything they killed
+    kills: Vec<&'a str>,
+    /// The dialogue trees of the conversations they finished
+    talks: Vec<&'a str>,
+    /// The regions they're standing in
+    regions: Vec<&'a str>,
+}
+
+/// Moves a quest's objectives along with what the player did this frame,
+/// completing the quest once every objective has been met
+fn progress(quest: &mut Quest, def: &QuestDef, deeds: &Deeds, inventory: &Inventory) {
+    quest.progress.resize(def.objectives.len(), 0);
+
+    for (objective, progress) in def.objectives.iter().zip(quest.progress.iter_mut()) {
+        *progress = match &objective.kind {
+            ObjectiveKind::Kill(prefab) => {
+                *progress + deeds.kills.iter().filter(|kill| *kill == prefab).count() as u32
+            }
+            ObjectiveKind::Collect(item) => inventory.count(item),
+            ObjectiveKind::Reach(region) if deeds.regions.contains(&region.as_str()) => {
+                objective.count
+            }
+            _ => *progress,
+        }
+        .min(objective.count);
+    }
+
+    // Conversations that hand a quest in are only checked once everything else is done
+    let met = |objective: &Objective, progress: u32| {
+        matches!(objective.kind, ObjectiveKind::TalkTo(_)) || progress >= objective.count
+    };
+    let ready = def
+        .objectives
+        .iter()
+        .zip(quest.progress.iter())
+        .all(|(objective, progress)| met(objective, *progress));
+
+    if ready {
+        for (objective, progress) in def.objectives.iter().zip(quest.progress.iter_mut()) {
+            if let ObjectiveKind::TalkTo(dialogue) = &objective.kind {
+                if deeds.talks.contains(&dialogue.as_str()) {
+                    *progress = objective.count;
+                }
+            }
+        }
+    }
+
+    let done = def
+        .objectives
+        .iter()
+        .zip(quest.progress.iter())
+        .all(|(objective, progress)| *progress >= objective.count);
+
+    if done {
+        quest.status = QuestStatus::Completed;
+    }
+}
+
+/// Legion systems for quests
+pub mod systems {
+    use super::*;
+
+    /// Moves each player's active quests along with their kills, finished
+    /// conversations, inventory and whereabouts this frame, and hands out
+    /// the rewards of quests once they're completed. Items that don't fit
+    /// in the inventory are dropped at the player's feet.
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(PrefabName)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[write_component(QuestLog)]
+    #[write_component(Inventory)]
+    #[write_component(Gold)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn track_quests(
+        world: &mut SubWorld,
+        commands: &mut CommandBuffer,
+        #[resource] death_queue: &DeathQueue,
+        #[resource] talk_queue: &mut TalkQueue,
+        #[resource] experience_queue: &mut ExperienceQueue,
+        #[resource] map: &TileMap,
+        #[resource] quest_info: &QuestInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let kills: Vec<(Entity, String)> = death_queue
+            .iter()
+            .filter_map(|death| {
+                let prefab = world.entry_ref(death.entity).ok()?;
+                let prefab = prefab.get_component::<PrefabName>().ok()?;
+                Some((death.killer?, prefab.0.clone()))
+            })
+            .collect();
+        let talks = std::mem::take(talk_queue);
+
+        for (entity, position, hurtbox, quest_log, inventory, gold, _) in <(
+            Entity,
+            &Position,
+            &Hurtbox,
+            &mut QuestLog,
+            &mut Inventory,
+            &mut Gold,
+            &IsPlayerCharacter,
+        )>::query()
+        .iter_mut(world)
+        {
+            let body = hurtbox.at(position);
+            let deeds = Deeds {
+                kills: kills
+                    .iter()
+                    .filter(|(killer, _)| killer == entity)
+                    .map(|(_, prefab)| prefab.as_str())
+                    .collect(),
+                talks: talks
+                    .iter()
+                    .filter(|talk| talk.player == *entity)
+                    .map(|talk| talk.dialogue.as_str())
+                    .collect(),
+                regions: map
+                    .objects_of_kind("region")
+                    .filter(|region| object_rect(region).has_intersection(body))
+                    .map(|region| region.name.as_str())
+                    .collect(),
+            };
+
+            for quest in quest_log.quests.iter_mut() {
+                let def = match quest_info.get(&quest.name) {
+                    Some(def) => def,
+                    None => continue,
+                };
+
+                if quest.status == QuestStatus::Active {
+                    progress(quest, def, &deeds, inventory);
+                }
+
+                if quest.status != QuestStatus::Completed || quest.rewarded {
+                    continue;
+                }
+                quest.rewarded = true;
+
+                for objective in def.objectives.iter() {
+                    if let ObjectiveKind::Collect(item) = &objective.kind {
+                        inventory.remove(item, objective.count);
+                    }
+                }
+
+                let rewards = &def.rewards;
+                gold.0 += rewards.gold;
+                if rewards.experience > 0 {
+                    experience_queue.push(ExperienceEvent {
+                        target: *entity,
+                        amount: rewards.experience,
+                    });
+                }
+                for (item, count) in rewards.items.iter() {
+                    let stack_size = item_info.get(item).map_or(1, |def| def.stack_size);
+                    let left = inventory.add(item, *count, stack_size);
+                    if left > 0 {
+                        inventory::spawn_ground_item(
+                            commands,
+                            item,
+                            left,
+                            (position.x, position.y),
+                            item_info,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lists each player's active quests and how far along
+    /// their objectives are in the corner of the screen
+    #[system(for_each)]
+    pub fn draw_quest_tracker(
+        _: &IsPlayerCharacter,
+        quest_log: &QuestLog,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] quest_info: &QuestInfo,
+    ) {
+        let quests: Vec<_> = quest_log
+            .quests
+            .iter()
+            .filter(|quest| quest.status == QuestStatus::Active)
+            .filter_map(|quest| {
+                let def = quest_info.get(&quest.name)?;
+                let objectives = def
+                    .objectives
+                    .iter()
+                    .enumerate()
+                    .map(|(i, objective)| {
+                        let progress = quest.progress.get(i).copied().unwrap_or(0);
+                        (objective.description.clone(), progress, objective.count)
+                    })
+                    .collect();
+                Some((def.name.clone(), objectives))
+            })
+            .collect();
+
+        if !quests.is_empty() {
+            render_queue.push(RenderRequest::QuestTracker(quests));
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use super::clock::WorldClock;
+use super::components::*;
+use super::farming::{self, Farms};
+use super::loot::OpenedChests;
+use super::migration;
+use super::player;
+use super::shop::{ShopInfo, Shops};
+use super::zone::{self, Destination, Transition, Zone, ZoneMap};
+use crate::rendering::{RenderQueue, RenderRequest};
+use legion::systems::CommandBuffer;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The version of the save format this build writes. Bump it whenever
+/// the format changes, and add a step to [migration] that upgrades saves
+/// from the version before.
+pub const SAVE_VERSION: u32 = 2;
+/// The directory saves are kept in
+pub const SAVE_DIR: &str = "Saves";
+/// How many slots players can save to themselves
+pub const SAVE_SLOTS: usize = 3;
+
+impl SaveSlot {
+    /// Where the slot's save file is kept
+    pub fn path(&self) -> PathBuf {
+        let file = match self {
+            SaveSlot::Autosave => String::from("autosave.ron"),
+            SaveSlot::Manual(slot) => format!("slot{}.ron", slot + 1),
+        };
+        PathBuf::from(SAVE_DIR).join(file)
+    }
+
+    /// What the save and load menus call the slot
+    pub fn name(&self) -> String {
+        match self {
+            SaveSlot::Autosave => String::from("Autosave"),
+            SaveSlot::Manual(slot) => format!("Slot {}", slot + 1),
+        }
+    }
+}
+
+/// Everything about the player that's kept between runs
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PlayerSave {
+    pub class: PlayerClass,
+    pub gender: Gender,
+    pub position: Position,
+    /// Where the player respawns
+    pub spawn: SpawnPoint,
+    pub stats: PlayerStats,
+    pub inventory: Inventory,
+    pub gold: Gold,
+    pub equipment: Equipment,
+    pub quest_log: QuestLog,
+    pub skills: Skills,
+}
+
+/// A saved game: the player, the zone they're in and the state of the world
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SaveFile {
+    /// The [SAVE_VERSION] the file was written with
+    pub version: u32,
+    pub zone: ZoneMap,
+    pub player: PlayerSave,
+    pub clock: WorldClock,
+    pub farms: Farms,
+    pub opened_chests: OpenedChests,
+    pub shops: Shops,
+}
+
+impl SaveFile {
+    /// Takes a snapshot of the game, or `None` if there's no player to save
+    pub fn capture(world: &World, resources: &Resources) -> Option<SaveFile> {
+        let player = <(Entity, &IsPlayerCharacter)>::query()
+            .iter(world)
+            .map(|(entity, _)| *entity)
+            .next()?;
+        let entry = world.entry_ref(player).ok()?;
+
+        let player = PlayerSave {
+            class: *entry.get_component::<PlayerClass>().ok()?,
+            gender: *entry.get_component::<Gender>().ok()?,
+            position: *entry.get_component::<Position>().ok()?,
+            spawn: *entry.get_component::<SpawnPoint>().ok()?,
+            stats: *entry.get_component::<PlayerStats>().ok()?,
+            inventory: Inventory {
+                open: false,
+                ..entry.get_component::<Inventory>().ok()?.clone()
+            },
+            gold: *entry.get_component::<Gold>().ok()?,
+            equipment: entry.get_component::<Equipment>().ok()?.clone(),
+            quest_log: entry.get_component::<QuestLog>().ok()?.clone(),
+            skills: entry.get_component::<Skills>().ok()?.clone(),
+        };
+
+        // The crops of the zone the player's in are still out in the world
+        let zone = resources.get::<Zone>()?.map.clone();
+        let mut farms = resources.get::<Farms>()?.clone();
+        farming::store_crops(world, &mut farms, zone.key());
+
+        Some(SaveFile {
+            version: SAVE_VERSION,
+            zone,
+            player,
+            clock: *resources.get::<WorldClock>()?,
+            farms,
+            opened_chests: resources.get::<OpenedChests>()?.clone(),
+            shops: resources.get::<Shops>()?.clone(),
+        })
+    }
+
+    /// Reads the save in a slot, upgrading it if an older version wrote it
+    pub fn read(slot: SaveSlot) -> Result<SaveFile, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(slot.path())?;
+        migration::read(&contents)
+    }
+
+    pub fn write(&self, slot: SaveSlot) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(SAVE_DIR)?;
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(slot.path(), contents)?;
+        Ok(())
+    }
+
+    /// A line describing the save for the load menu
+    pub fn summary(&self) -> String {
+        let place = match &self.zone {
+            ZoneMap::Tiled(_) => "Town",
+            ZoneMap::Dungeon(_) => "Dungeon",
+        };
+        format!(
+            "Day {} Lv {} {}",
+            self.clock.day() + 1,
+            self.player.stats.level,
+            place
+        )
+    }
+
+    /// Replaces the game with the saved one: every entity is removed, the
+    /// world's state is put back, and a fresh player made from the save is
+    /// put back where they were in the zone they were in
+    pub fn restore(
+        self,
+        world: &mut World,
+        resources: &mut Resources,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The map's loaded first so that nothing's touched if it can't be
+        let map = zone::load_map(&self.zone, resources)?;
+
+        world.clear();
+
+        let saved = self.player;
+        let (x, y) = (saved.position.x, saved.position.y);
+        let player = player::new(world, (x, y), saved.class, saved.gender);
+        if let Some(mut entry) = world.entry(player) {
+            entry.add_component(saved.stats);
+            entry.add_component(saved.inventory);
+            entry.add_component(saved.gold);
+            entry.add_component(saved.equipment);
+            entry.add_component(saved.quest_log);
+            entry.add_component(saved.skills);
+        }
+
+        resources.insert(self.clock);
+        resources.insert(self.farms);
+        resources.insert(self.opened_chests);
+        // Shops may have changed since the save, so its stock is fitted to them
+        let shops = Shops::from_save(&self.shops, &resources.get::<ShopInfo>().unwrap());
+        resources.insert(shops);
+        resources.get_mut::<Zone>().unwrap().transition = Transition::None;
+
+        let destination = Destination {
+            map: self.zone,
+            entrance: None,
+        };
+        zone::arrive(world, resources, &destination, map);
+
+        // Entering a zone puts players at its start, so they're moved back
+        for (position, spawn, _) in
+            <(&mut Position, &mut SpawnPoint, &IsPlayerCharacter)>::query().iter_mut(world)
+        {
+            *position = saved.position;
+            *spawn = saved.spawn;
+        }
+
+        Ok(())
+    }
+}
+
+/// Saves the game to the autosave slot
+pub fn autosave(world: &World, resources: &Resources) {
+    handle_save(world, resources, SaveSlot::Autosave);
+}
+
+fn handle_save(world: &World, resources: &Resources, slot: SaveSlot) {
+    let save = match SaveFile::capture(world, resources) {
+        Some(save) => save,
+        None => return println!("Nothing to save"),
+    };
+
+    match save.write(slot) {
+        Ok(()) => println!("Saved to {}", slot.name()),
+        Err(error) => println!("Couldn't save to {}: {}", slot.name(), error),
+    }
+}
+
+/// Carries out the saves and loads asked for from the menus. This needs
+/// the whole world, so it's done between frames rather than by a system.
+pub fn handle_requests(world: &mut World, resources: &mut Resources) {
+    let requests = std::mem::take(&mut *resources.get_mut::<SaveQueue>().unwrap());
+
+    for request in requests {
+        match request {
+            SaveRequest::Save(slot) => handle_save(world, resources, slot),
+            SaveRequest::Load(slot) => {
+                let loaded = SaveFile::read(slot).and_then(|save| save.restore(world, resources));
+                match loaded {
+                    Ok(()) => println!("Loaded {}", slot.name()),
+                    Err(error) => println!("Couldn't load {}: {}", slot.name(), error),
+                }
+            }
+        }
+    }
+}
+
+/// Each slot a menu offers, with a summary of what's in it. The load
+/// menu offers the autosave as well as the slots players save to.
+fn slots(loading: bool) -> Vec<(SaveSlot, Option<String>)> {
+    let autosave = if loading {
+        Some(SaveSlot::Autosave)
+    } else {
+        None
+    };
+
+    autosave
+        .into_iter()
+        .chain((0..SAVE_SLOTS).map(SaveSlot::Manual))
+        .map(|slot| {
+            let summary = if slot.path().exists() {
+                Some(
+                    SaveFile::read(slot)
+                        .map_or_else(|_| String::from("Can't be read"), |save| save.summary()),
+                )
+            } else {
+                None
+            };
+            (slot, summary)
+        })
+        .collect()
+}
+
+/// Legion systems for saving and loading
+pub mod systems {
+    use super::*;
+
+    /// Opens the save and load menus, and picks a slot from them. While one's
+    /// open it takes all of the player's input, so the player stands still.
+    #[system(for_each)]
+    pub fn use_save_menu(
+        entity: &Entity,
+        _: &IsPlayerCharacter,
+        menu: Option<&mut SaveMenu>,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] save_queue: &mut SaveQueue,
+    ) {
+        let menu = match menu {
+            Some(menu) => menu,
+            None => {
+                let loading = if input.contains(&Input::LoadGame) {
+                    true
+                } else if input.contains(&Input::SaveGame) {
+                    false
+                } else {
+                    return;
+                };
+
+                input.retain(|input| !matches!(input, Input::SaveGame | Input::LoadGame));
+                commands.add_component(
+                    *entity,
+                    SaveMenu {
+                        loading,
+                        slots: slots(loading),
+                        selected: 0,
+                    },
+                );
+                return;
+            }
+        };
+
+        for input in std::mem::take(input) {
+            match input {
+                Input::Back | Input::SaveGame | Input::LoadGame => {
+                    commands.remove_component::<SaveMenu>(*entity);
+                    return;
+                }
+                Input::Navigate(Direction::Up) => {
+                    menu.selected = menu.selected.saturating_sub(1);
+                }
+                Input::Navigate(Direction::Down) => {
+                    menu.selected = (menu.selected + 1).min(menu.slots.len().saturating_sub(1));
+                }
+                Input::Interact | Input::UseItem => {
+                    let (slot, summary) = match menu.slots.get(menu.selected) {
+                        Some(row) => row,
+                        None => continue,
+                    };
+
+                    if !menu.loading {
+                        save_queue.push(SaveRequest::Save(*slot));
+                    } else if summary.is_some() {
+                        save_queue.push(SaveRequest::Load(*slot));
+                    } else {
+                        // There's nothing to load from an empty slot
+                        continue;
+                    }
+                    commands.remove_component::<SaveMenu>(*entity);
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Puts up the save or load menu for each player who has one open
+    #[system(for_each)]
+    pub fn draw_save_menu(
+        _: &IsPlayerCharacter,
+        menu: &SaveMenu,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        let rows = menu
+            .slots
+            .iter()
+            .map(|(slot, summary)| {
+                format!(
+                    "{:<10}{}",
+                    slot.name(),
+                    summary.as_deref().unwrap_or("Empty")
+                )
+            })
+            .collect();
+
+        render_queue.push(RenderRequest::SaveMenu {
+            name: String::from(if menu.loading { "Load" } else { "Save" }),
+            rows,
+            selected: menu.selected,
+        });
+    }
+}
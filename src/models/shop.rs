@@ -0,0 +1,333 @@
+use super::components::*;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{ItemCategory, ItemInfo};
+use legion::systems::CommandBuffer;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn full_price() -> u32 {
+    100
+}
+
+/// Something a shop sells
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ShopItem {
+    pub item: String,
+    /// What the shop charges, if it's not the item's usual value
+    #[serde(default)]
+    pub price: Option<u32>,
+    /// How many the shop has when it's fully stocked
+    pub stock: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ShopDef {
+    /// The name shown at the top of the shop screen
+    pub name: String,
+    pub items: Vec<ShopItem>,
+    /// How often the shop is stocked back up, in frames
+    pub restock_frames: u32,
+    /// How much the shop pays for items, as a percentage of their value
+    #[serde(default = "full_price")]
+    pub buy_back: u32,
+}
+
+impl ShopDef {
+    /// What the shop charges for its `index`-th item
+    pub fn price(&self, index: usize, item_info: &ItemInfo) -> u32 {
+        let item = &self.items[index];
+        item.price
+            .unwrap_or_else(|| item_info.get(&item.item).map_or(0, |def| def.value))
+    }
+
+    /// What the shop pays for an item, or `None` if it won't buy it
+    pub fn offer(&self, item: &str, item_info: &ItemInfo) -> Option<u32> {
+        let def = item_info.get(item)?;
+        if def.value == 0 || def.category == ItemCategory::Currency {
+            return None;
+        }
+        Some((def.value * self.buy_back / 100).max(1))
+    }
+}
+
+/// Every shop, by name
+#[derive(Deserialize, Serialize)]
+pub struct ShopInfo {
+    pub shops: HashMap<String, ShopDef>,
+}
+
+impl ShopInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let shop_info = ron::from_str(&contents)?;
+        Ok(shop_info)
+    }
+
+    pub fn get(&self, shop: &str) -> Option<&ShopDef> {
+        self.shops.get(shop)
+    }
+}
+
+/// What a shop has left on its shelves
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ShopStock {
+    /// How many of each of the shop's items are left, in the order they're defined in
+    pub counts: Vec<u32>,
+    /// How long until the shop's stocked back up, in frames
+    pub restock_in: u32,
+}
+
+/// The stock of every shop, by name. Shops keep their stock while
+/// nobody's around to see it, so this lives outside of any zone.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Shops(pub HashMap<String, ShopStock>);
+
+impl Shops {
+    /// Every shop, fully stocked
+    pub fn new(shop_info: &ShopInfo) -> Self {
+        Shops(
+            shop_info
+                .shops
+                .iter()
+                .map(|(name, def)| (name.clone(), stocked(def)))
+                .collect(),
+        )
+    }
+
+    /// Every shop with the stock it had in a save. Shops are matched to their
+    /// stock by name and items by their place in the shop, so shops and items
+    /// added since the save are fully stocked, and ones that are gone are dropped.
+    pub fn from_save(saved: &Shops, shop_info: &ShopInfo) -> Self {
+        let mut shops = Shops::new(shop_info);
+
+        for (name, stock) in shops.0.iter_mut() {
+            let saved = match saved.0.get(name) {
+                Some(saved) => saved,
+                None => continue,
+            };
+
+            for (count, saved) in stock.counts.iter_mut().zip(saved.counts.iter()) {
+                *count = *saved;
+            }
+            stock.restock_in = saved.restock_in.min(stock.restock_in);
+        }
+
+        shops
+    }
+}
+
+fn stocked(def: &ShopDef) -> ShopStock {
+    ShopStock {
+        counts: def.items.iter().map(|item| item.stock).collect(),
+        restock_in: def.restock_frames,
+    }
+}
+
+/// The inventory slots holding something the shop will buy, in order
+fn sellable(inventory: &Inventory, def: &ShopDef, item_info: &ItemInfo) -> Vec<usize> {
+    inventory
+        .slots
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, stack)| {
+            def.offer(&stack.as_ref()?.item, item_info)?;
+            Some(slot)
+        })
+        .collect()
+}
+
+/// Legion systems for shops
+pub mod systems {
+    use super::*;
+
+    /// Counts down to each shop's next restock, filling its shelves back up when it's time
+    #[system]
+    pub fn restock_shops(#[resource] shops: &mut Shops, #[resource] shop_info: &ShopInfo) {
+        for (name, stock) in shops.0.iter_mut() {
+            stock.restock_in = stock.restock_in.saturating_sub(1);
+            if stock.restock_in > 0 {
+                continue;
+            }
+
+            if let Some(def) = shop_info.get(name) {
+                *stock = stocked(def);
+            }
+        }
+    }
+
+    /// Buys and sells items for players browsing a shop, one at a time.
+    /// Left and right switch between buying and selling, and players can't
+    /// do anything else until they leave.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn trade(
+        entity: &Entity,
+        shopping: &mut Shopping,
+        inventory: &mut Inventory,
+        gold: &mut Gold,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+        #[resource] shops: &mut Shops,
+        #[resource] shop_info: &ShopInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let pressed = std::mem::take(input);
+
+        let (def, stock) = match (
+            shop_info.get(&shopping.shop),
+            shops.0.get_mut(&shopping.shop),
+        ) {
+            (Some(def), Some(stock)) => (def, stock),
+            _ => {
+                println!("No shop named {}", shopping.shop);
+                commands.remove_component::<Shopping>(*entity);
+                return;
+            }
+        };
+
+        for input in pressed {
+            let rows = if shopping.selling {
+                sellable(inventory, def, item_info).len()
+            } else {
+                def.items.len()
+            };
+
+            match input {
+                Input::Back | Input::ToggleInventory => {
+                    commands.remove_component::<Shopping>(*entity);
+                    return;
+                }
+                Input::Navigate(Direction::Left) | Input::Navigate(Direction::Right) => {
+                    shopping.selling = !shopping.selling;
+                    shopping.selected = 0;
+                }
+                Input::Navigate(Direction::Up) => {
+                    shopping.selected = shopping.selected.saturating_sub(1);
+                }
+                Input::Navigate(Direction::Down) => {
+                    shopping.selected = (shopping.selected + 1).min(rows.saturating_sub(1));
+                }
+                Input::UseItem | Input::Interact if shopping.selling => {
+                    let slot = match sellable(inventory, def, item_info).get(shopping.selected) {
+                        Some(slot) => *slot,
+                        None => continue,
+                    };
+                    if let Some(sold) = inventory.take(slot, 1) {
+                        gold.0 += def.offer(&sold.item, item_info).unwrap_or(0);
+                    }
+
+                    let rows = sellable(inventory, def, item_info).len();
+                    shopping.selected = shopping.selected.min(rows.saturating_sub(1));
+                }
+                Input::UseItem | Input::Interact => {
+                    let index = shopping.selected;
+                    let (item, price) = match def.items.get(index) {
+                        Some(item) => (&item.item, def.price(index, item_info)),
+                        None => continue,
+                    };
+
+                    let count = match stock.counts.get_mut(index) {
+                        Some(count) if *count > 0 && gold.0 >= price => count,
+                        _ => continue,
+                    };
+
+                    let stack_size = item_info.get(item).map_or(1, |def| def.stack_size);
+                    if inventory.add(item, 1, stack_size) == 0 {
+                        *count -= 1;
+                        gold.0 -= price;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Puts up the shop screen for each player browsing a shop
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_shop(
+        _: &IsPlayerCharacter,
+        shopping: &Shopping,
+        inventory: &Inventory,
+        gold: &Gold,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] shops: &Shops,
+        #[resource] shop_info: &ShopInfo,
+        #[resource] item_info: &ItemInfo,
+    ) {
+        let (def, stock) = match (shop_info.get(&shopping.shop), shops.0.get(&shopping.shop)) {
+            (Some(def), Some(stock)) => (def, stock),
+            _ => return,
+        };
+
+        let rows = if shopping.selling {
+            sellable(inventory, def, item_info)
+                .into_iter()
+                .filter_map(|slot| {
+                    let stack = inventory.slots[slot].as_ref()?;
+                    let item = item_info.get(&stack.item)?;
+                    let price = def.offer(&stack.item, item_info)?;
+                    Some((item.icon, item.name.clone(), price, stack.count))
+                })
+                .collect()
+        } else {
+            def.items
+                .iter()
+                .enumerate()
+                .filter_map(|(index, shop_item)| {
+                    let item = item_info.get(&shop_item.item)?;
+                    let price = def.price(index, item_info);
+                    let count = stock.counts.get(index).copied().unwrap_or(0);
+                    Some((item.icon, item.name.clone(), price, count))
+                })
+                .collect()
+        };
+
+        render_queue.push(RenderRequest::Shop {
+            name: def.name.clone(),
+            selling: shopping.selling,
+            rows,
+            selected: shopping.selected,
+            gold: gold.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_stock_is_fitted_to_the_shops_as_they_are_now() {
+        let shop_info = ShopInfo::from("Assets/GameData/shops.ron").unwrap();
+        let mut saved = HashMap::new();
+        saved.insert(
+            "general_store".to_string(),
+            ShopStock {
+                counts: vec![5, 3, 0],
+                restock_in: 400,
+            },
+        );
+        saved.insert(
+            "closed_down".to_string(),
+            ShopStock {
+                counts: vec![1],
+                restock_in: 10,
+            },
+        );
+
+        let shops = Shops::from_save(&Shops(saved), &shop_info);
+
+        assert_eq!(shops, Shops::from_save(&shops, &shop_info));
+        assert!(!shops.0.contains_key("closed_down"));
+        assert_eq!(
+            shops.0["blacksmith"],
+            Shops::new(&shop_info).0["blacksmith"]
+        );
+        let general_store = &shops.0["general_store"];
+        assert_eq!(general_store.counts[..3], [5, 3, 0]);
+        assert_eq!(general_store.counts[3..], [3, 1, 10, 10, 5]);
+        assert_eq!(general_store.restock_in, 400);
+    }
+}
@@ -0,0 +1,173 @@
+use super::components::*;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::ExperienceCurve;
+use legion::systems::CommandBuffer;
+use legion::world::SubWorld;
+use legion::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Something that's out of reach until a skill's levelled up far enough
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Unlock {
+    /// Crafting or cooking the named recipe
+    Recipe(String),
+    /// Using, equipping or planting the named item, like a tool or a seed
+    Item(String),
+    /// Going through the named portal
+    Area(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SkillUnlock {
+    pub level: u32,
+    pub unlock: Unlock,
+    /// What the skills page calls the unlock
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SkillDef {
+    /// The name shown on the skills page
+    pub name: String,
+    pub experience_curve: ExperienceCurve,
+    #[serde(default)]
+    pub unlocks: Vec<SkillUnlock>,
+}
+
+impl SkillDef {
+    /// The first unlock past the given level
+    pub fn next_unlock(&self, level: u32) -> Option<&SkillUnlock> {
+        self.unlocks
+            .iter()
+            .filter(|unlock| unlock.level > level)
+            .min_by_key(|unlock| unlock.level)
+    }
+}
+
+/// Every skill's curve and unlocks
+#[derive(Deserialize, Serialize)]
+pub struct SkillInfo {
+    pub skills: HashMap<Skill, SkillDef>,
+}
+
+impl SkillInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let skill_info = ron::from_str(&contents)?;
+        Ok(skill_info)
+    }
+
+    pub fn get(&self, skill: Skill) -> Option<&SkillDef> {
+        self.skills.get(&skill)
+    }
+
+    /// The skill and level needed for something, if a skill gates it at all
+    pub fn requirement(&self, unlock: &Unlock) -> Option<(Skill, u32)> {
+        self.skills.iter().find_map(|(skill, def)| {
+            def.unlocks
+                .iter()
+                .find(|skill_unlock| skill_unlock.unlock == *unlock)
+                .map(|skill_unlock| (*skill, skill_unlock.level))
+        })
+    }
+
+    /// Whether a player's skills are far enough along for something
+    pub fn allows(&self, skills: &Skills, unlock: &Unlock) -> bool {
+        self.requirement(unlock)
+            .is_none_or(|(skill, level)| skills.level(skill) >= level)
+    }
+
+    /// Why a player can't have something yet, if they can't
+    pub fn refusal(&self, skills: &Skills, unlock: &Unlock) -> Option<String> {
+        let (skill, level) = self
+            .requirement(unlock)
+            .filter(|(skill, level)| skills.level(*skill) < *level)?;
+        let name = self.get(skill).map_or("", |def| def.name.as_str());
+        Some(format!("Needs {} level {}", name, level))
+    }
+}
+
+/// Legion systems for life skills
+pub mod systems {
+    use super::*;
+
+    /// Drains the skill experience queue into each target's skills
+    #[system]
+    #[write_component(Skills)]
+    pub fn apply_skill_experience(
+        world: &mut SubWorld,
+        #[resource] skill_experience_queue: &mut SkillExperienceQueue,
+        #[resource] skill_info: &SkillInfo,
+    ) {
+        for event in skill_experience_queue.drain(..) {
+            let def = match skill_info.get(event.skill) {
+                Some(def) => def,
+                None => {
+                    println!("No skill definition for {:?}", event.skill);
+                    continue;
+                }
+            };
+
+            let skills = match world.entry_mut(event.target) {
+                Ok(entry) => match entry.into_component_mut::<Skills>() {
+                    Ok(skills) => skills,
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            };
+
+            if skills.gain(event.skill, event.amount, &def.experience_curve) {
+                println!("{} is now level {}", def.name, skills.level(event.skill));
+            }
+        }
+    }
+
+    /// Opens and closes the skills page. While it's open it
+    /// takes all of the player's input, so the player stands still.
+    #[system(for_each)]
+    pub fn view_skills(
+        entity: &Entity,
+        _: &IsPlayerCharacter,
+        page: Option<&SkillsPage>,
+        commands: &mut CommandBuffer,
+        #[resource] input: &mut InputQueue,
+    ) {
+        if page.is_some() {
+            let pressed = std::mem::take(input);
+            if pressed
+                .iter()
+                .any(|input| matches!(input, Input::ToggleSkills | Input::Back))
+            {
+                commands.remove_component::<SkillsPage>(*entity);
+            }
+        } else if input.contains(&Input::ToggleSkills) {
+            input.retain(|input| *input != Input::ToggleSkills);
+            commands.add_component(*entity, SkillsPage);
+        }
+    }
+
+    /// Puts up the skills page for each player who has it open
+    #[system(for_each)]
+    pub fn draw_skills(
+        _: &IsPlayerCharacter,
+        _: &SkillsPage,
+        skills: &Skills,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] skill_info: &SkillInfo,
+    ) {
+        let rows = SKILLS
+            .iter()
+            .filter_map(|skill| {
+                let def = skill_info.get(*skill)?;
+                let progress = skills.get(*skill, &def.experience_curve);
+                let next = def
+                    .next_unlock(progress.level)
+                    .map(|unlock| (unlock.level, unlock.description.clone()));
+                Some((def.name.clone(), progress.level, progress.experience, next))
+            })
+            .collect();
+
+        render_queue.push(RenderRequest::Skills(rows));
+    }
+}
@@ -0,0 +1,122 @@
+use super::components::*;
+use legion::serialize::Canon;
+use legion::*;
+use serde::de::DeserializeSeed;
+
+/// Registers each component type under its own name
+macro_rules! register {
+    ($registry:expr, $($component:ty),* $(,)?) => {
+        $($registry.register::<$component>(String::from(stringify!($component)));)*
+    };
+}
+
+/// Every component, keyed by its name in dumped worlds. Dumps are read
+/// back by these names, so renaming a component breaks older dumps.
+pub fn registry() -> Registry<String> {
+    let mut registry = Registry::default();
+
+    register!(
+        registry,
+        Position,
+        PlayerClass,
+        Gender,
+        PlayerStats,
+        PlayerState,
+        Id,
+        IsPlayerCharacter,
+        Global,
+        Invulnerability,
+        StateTimer,
+        SpawnPoint,
+        Hurtbox,
+        Hitbox,
+        MeleeState,
+        Abilities,
+        Velocity,
+        Projectile,
+        ExperienceReward,
+        LevelUpNotice,
+        Faction,
+        PrefabName,
+        DirectionalSprite,
+        StaticSprite,
+        Npc,
+        NpcSchedule,
+        GroundItem,
+        LootTable,
+        Chest,
+        Despawn,
+        Gold,
+        EnemyStats,
+        Ai,
+        Inventory,
+        QuestLog,
+        Skills,
+        SkillsPage,
+        Fishing,
+        Crop,
+        Shopping,
+        Crafting,
+        SaveMenu,
+        Conversation,
+        Buffs,
+        EffectiveStats,
+        Equipment,
+        StatusEffects,
+    );
+
+    registry
+}
+
+/// Writes out every entity in the world and its components as RON.
+/// References between entities are kept, so they hold up once loaded.
+pub fn dump(world: &World, registry: &Registry<String>) -> Result<String, ron::Error> {
+    let canon = Canon::default();
+    let serializable = world.as_serializable(any(), registry, &canon);
+    ron::ser::to_string_pretty(&serializable, ron::ser::PrettyConfig::default())
+}
+
+/// Reads a dumped world back into a new one
+pub fn load(contents: &str, registry: &Registry<String>) -> Result<World, ron::Error> {
+    let canon = Canon::default();
+    let mut deserializer = ron::Deserializer::from_str(contents)?;
+    registry
+        .as_deserialize(&canon)
+        .deserialize(&mut deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn worlds_round_trip() {
+        let registry = registry();
+        let mut world = World::default();
+        let npc = world.push((
+            Npc {
+                dialogue: Some(String::from("villager")),
+            },
+            SpawnPoint { x: 416, y: 96 },
+        ));
+        world.push((
+            IsPlayerCharacter,
+            Gold(25),
+            Conversation::new(npc, "villager", "start"),
+        ));
+
+        let loaded = load(&dump(&world, &registry).unwrap(), &registry).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        let (conversation, gold) = <(&Conversation, &Gold)>::query()
+            .iter(&loaded)
+            .next()
+            .unwrap();
+        assert_eq!(*gold, Gold(25));
+        let npc = loaded.entry_ref(conversation.npc).unwrap();
+        assert_eq!(
+            *npc.get_component::<SpawnPoint>().unwrap(),
+            SpawnPoint { x: 416, y: 96 }
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use super::components::*;
+use crate::util::{ItemInfo, ProgressionInfo};
+use legion::*;
+
+/// Adds a bonus that might be negative to a stat, stopping at zero
+fn apply(stat: u32, bonus: i32) -> u32 {
+    (stat as i64 + bonus as i64).max(0) as u32
+}
+
+/// The sum of the bonuses of everything in `equipment`
+pub fn equipment_bonus(equipment: &Equipment, item_info: &ItemInfo) -> StatBonus {
+    let mut bonus = StatBonus::default();
+
+    for slot in EQUIPMENT_SLOTS.iter() {
+        if let Some(def) = equipment
+            .slot(*slot)
+            .as_ref()
+            .and_then(|item| item_info.get(item))
+        {
+            bonus += def.stats.bonus();
+        }
+    }
+
+    bonus
+}
+
+/// Works out a character's effective stats: their class's base stats,
+/// plus its growth for every level past the first, plus the bonuses from
+/// their equipment and buffs. Max health never drops below one.
+pub fn calculate(
+    class: PlayerClass,
+    level: u32,
+    bonus: StatBonus,
+    progression_info: &ProgressionInfo,
+) -> EffectiveStats {
+    let base = progression_info.base(class);
+    let growth = progression_info.growth(class);
+    let levels = level.saturating_sub(1);
+
+    EffectiveStats {
+        attack: apply(base.attack + growth.attack * levels, bonus.attack),
+        defence: apply(base.defence + growth.defence * levels, bonus.defence),
+        max_health: apply(base.health + growth.health * levels, bonus.max_health).max(1),
+        max_mana: apply(base.mana + growth.mana * levels, bonus.max_mana),
+    }
+}
+
+/// Works out a character's effective stats at their current level, and
+/// keeps the maximums of their health and mana in line with them. This is
+/// the only place those maximums are set. Health and mana are cut down to
+/// fit if their maximums drop, e.g. when equipment comes off.
+pub fn update(
+    class: PlayerClass,
+    stats: &mut PlayerStats,
+    equipment: &Equipment,
+    buffs: Option<&Buffs>,
+    item_info: &ItemInfo,
+    progression_info: &ProgressionInfo,
+) -> EffectiveStats {
+    let mut bonus = equipment_bonus(equipment, item_info);
+    if let Some(buffs) = buffs {
+        bonus += buffs.0;
+    }
+
+    let effective = calculate(class, stats.level, bonus, progression_info);
+
+    stats.health.max = effective.max_health;
+    stats.health.current = stats.health.current.min(stats.health.max);
+    stats.mana.max = effective.max_mana;
+    stats.mana.current = stats.mana.current.min(stats.mana.max);
+
+    effective
+}
+
+/// Legion systems for working out stats
+pub mod systems {
+    use super::*;
+
+    /// Recalculates each character's effective stats, so they keep up
+    /// with changes to their equipment and buffs
+    #[system(for_each)]
+    pub fn calculate_stats(
+        class: &PlayerClass,
+        stats: &mut PlayerStats,
+        equipment: &Equipment,
+        buffs: Option<&Buffs>,
+        effective: &mut EffectiveStats,
+        #[resource] item_info: &ItemInfo,
+        #[resource] progression_info: &ProgressionInfo,
+    ) {
+        *effective = update(*class, stats, equipment, buffs, item_info, progression_info);
+    }
+}
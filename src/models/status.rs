@@ -0,0 +1,176 @@
+use super::components::*;
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::{Stacking, StatusInfo};
+use legion::world::SubWorld;
+use legion::*;
+
+/// The most a character can be slowed by, as a percentage,
+/// so that stacked slows never stop them in their tracks
+pub const MAX_SLOW: u32 = 80;
+
+/// Inflicts a status effect on a character, following the
+/// effect's stacking rule if they already have it
+pub fn inflict(effects: &mut StatusEffects, event: &StatusEvent, status_info: &StatusInfo) {
+    let info = status_info.get(event.status);
+
+    match effects
+        .0
+        .iter_mut()
+        .find(|effect| effect.status == event.status)
+    {
+        Some(effect) => {
+            match info.stacking {
+                Stacking::Refresh => effect.frames_left = info.duration,
+                Stacking::Extend => effect.frames_left += info.duration,
+                Stacking::Intensify(max_stacks) => {
+                    effect.stacks = (effect.stacks + 1).min(max_stacks);
+                    effect.frames_left = info.duration;
+                }
+            }
+            effect.source = event.source.or(effect.source);
+        }
+        None => effects.0.push(StatusEffect {
+            status: event.status,
+            source: event.source,
+            frames_left: info.duration,
+            stacks: 1,
+            tick: info.interval,
+        }),
+    }
+}
+
+/// How fast a character with the given effects moves, as a percentage
+/// of their usual speed
+pub fn speed_percent(effects: &StatusEffects, status_info: &StatusInfo) -> u32 {
+    let slow: u32 = effects
+        .0
+        .iter()
+        .map(|effect| status_info.get(effect.status).slow * effect.stacks)
+        .sum();
+
+    100 - slow.min(MAX_SLOW)
+}
+
+/// Legion systems for status effects
+pub mod systems {
+    use super::*;
+
+    /// Drains the status queue, inflicting each effect on its target. Targets
+    /// that are dead shrug effects off, as do invulnerable targets unless
+    /// they inflicted the effect on themselves, e.g. by drinking a potion.
+    #[system]
+    #[read_component(Invulnerability)]
+    #[read_component(PlayerState)]
+    #[write_component(StatusEffects)]
+    pub fn apply_status_effects(
+        world: &mut SubWorld,
+        #[resource] status_queue: &mut StatusQueue,
+        #[resource] status_info: &StatusInfo,
+    ) {
+        for event in status_queue.drain(..) {
+            let mut entry = match world.entry_mut(event.target) {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            let invulnerable = entry
+                .get_component::<Invulnerability>()
+                .is_ok_and(|invulnerability| invulnerability.0 > 0);
+            let dead = entry
+                .get_component::<PlayerState>()
+                .is_ok_and(|state| *state == PlayerState::Death);
+
+            if (invulnerable && event.source != Some(event.target)) || dead {
+                continue;
+            }
+
+            if let Ok(effects) = entry.get_component_mut::<StatusEffects>() {
+                inflict(effects, &event, status_info);
+            }
+        }
+    }
+
+    /// Counts down each character's status effects, queueing damage and
+    /// restoring health as they tick, and totals up the stat bonuses of
+    /// those still active into the character's buffs. Death clears every
+    /// effect.
+    #[system(for_each)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn tick_status_effects(
+        entity: &Entity,
+        effects: &mut StatusEffects,
+        state: &PlayerState,
+        buffs: Option<&mut Buffs>,
+        player_stats: Option<&mut PlayerStats>,
+        enemy_stats: Option<&mut EnemyStats>,
+        #[resource] damage_queue: &mut DamageQueue,
+        #[resource] status_info: &StatusInfo,
+    ) {
+        if *state == PlayerState::Death {
+            effects.0.clear();
+        }
+
+        let mut heal = 0;
+        let mut bonus = StatBonus::default();
+
+        for effect in effects.0.iter_mut() {
+            let info = status_info.get(effect.status);
+
+            effect.frames_left = effect.frames_left.saturating_sub(1);
+            effect.tick = effect.tick.saturating_sub(1);
+
+            if info.interval > 0 && effect.tick == 0 {
+                effect.tick = info.interval;
+
+                if info.damage > 0 {
+                    damage_queue.push(DamageEvent {
+                        target: *entity,
+                        source: effect.source,
+                        amount: info.damage * effect.stacks,
+                        over_time: true,
+                    });
+                }
+                heal += info.heal * effect.stacks;
+            }
+
+            for _ in 0..effect.stacks {
+                bonus += info.bonus;
+            }
+        }
+
+        effects.0.retain(|effect| effect.frames_left > 0);
+
+        if heal > 0 {
+            if let Some(stats) = player_stats {
+                stats.health.restore(heal);
+            } else if let Some(stats) = enemy_stats {
+                stats.health.restore(heal);
+            }
+        }
+
+        if let Some(buffs) = buffs {
+            buffs.0 = bonus;
+        }
+    }
+
+    /// Puts the icons of each player's status effects next to their portrait
+    #[system(for_each)]
+    pub fn draw_status_icons(
+        _: &IsPlayerCharacter,
+        effects: &StatusEffects,
+        #[resource] render_queue: &mut RenderQueue,
+        #[resource] status_info: &StatusInfo,
+    ) {
+        if effects.0.is_empty() {
+            return;
+        }
+
+        render_queue.push(RenderRequest::StatusIcons(
+            effects
+                .0
+                .iter()
+                .map(|effect| (status_info.get(effect.status).icon, effect.stacks))
+                .collect(),
+        ));
+    }
+}
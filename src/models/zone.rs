@@ -0,0 +1,306 @@
+use super::components::*;
+use super::farming::{self, FarmingInfo, Farms};
+use super::loot::{self, OpenedChests};
+use super::prefab::PrefabInfo;
+use super::skills::{SkillInfo, Unlock};
+use crate::map::autotile::AutotileInfo;
+use crate::map::dungeon::{self, DungeonSettings};
+use crate::map::{MapObject, TileMap};
+use crate::rendering::{RenderQueue, RenderRequest};
+use crate::util::Rng;
+use legion::query::component;
+use legion::world::SubWorld;
+use legion::*;
+use sdl2::rect::Rect;
+use serde::{Deserialize, Serialize};
+
+/// How long the screen takes to fade out when leaving
+/// a zone, and to fade back in when arriving in the next
+pub const FADE_FRAMES: u32 = 10;
+
+/// The map a zone is made from
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ZoneMap {
+    /// A map loaded from the tiled file at the given path
+    Tiled(String),
+    /// A dungeon generated from the given seed
+    Dungeon(u64),
+}
+
+impl ZoneMap {
+    /// What data about the zone, like what can be caught there, is keyed
+    /// by: the path of a tiled map, or `dungeon` for generated dungeons
+    pub fn key(&self) -> &str {
+        match self {
+            ZoneMap::Tiled(path) => path,
+            ZoneMap::Dungeon(_) => "dungeon",
+        }
+    }
+}
+
+/// Where a portal leads: a map, and the `entrance` object within it
+/// that players arrive at. Without an entrance, players arrive at the
+/// map's `player_start`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Destination {
+    pub map: ZoneMap,
+    pub entrance: Option<String>,
+}
+
+impl Destination {
+    /// Reads the destination of a `portal` object. Its `map` property is the
+    /// path of a tiled map, or `dungeon` for a freshly generated dungeon,
+    /// which is made from the `seed` property if there is one.
+    pub fn from_portal(portal: &MapObject, rng: &mut Rng) -> Option<Destination> {
+        let map = match portal.string_property("map")? {
+            "dungeon" => ZoneMap::Dungeon(
+                portal
+                    .int_property("seed")
+                    .map_or_else(|| rng.next_u64(), |seed| seed as u64),
+            ),
+            path => ZoneMap::Tiled(String::from(path)),
+        };
+
+        Some(Destination {
+            map,
+            entrance: portal.string_property("entrance").map(String::from),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transition {
+    None,
+    /// Fading out before heading to `destination`
+    Leaving {
+        destination: Destination,
+        frames_left: u32,
+    },
+    /// Fading in after arriving in a zone
+    Arriving {
+        frames_left: u32,
+    },
+}
+
+/// The zone the players are in, and whether they're on their way to another
+pub struct Zone {
+    pub map: ZoneMap,
+    pub transition: Transition,
+    /// Portals only work once players have stepped off the one they
+    /// arrived on, so that arriving on a portal doesn't send them straight back
+    pub armed: bool,
+}
+
+impl Zone {
+    pub fn new(map: ZoneMap) -> Self {
+        Zone {
+            map,
+            transition: Transition::None,
+            armed: false,
+        }
+    }
+
+    /// The destination to head to now, once the screen has faded out
+    pub fn ready_to_leave(&self) -> Option<&Destination> {
+        match &self.transition {
+            Transition::Leaving {
+                destination,
+                frames_left: 0,
+            } => Some(destination),
+            _ => None,
+        }
+    }
+}
+
+/// The area a tiled object covers
+pub fn object_rect(object: &MapObject) -> Rect {
+    Rect::new(object.x, object.y, object.width, object.height)
+}
+
+/// Loads the map of a zone, generating it if it's a dungeon
+pub fn load_map(
+    map: &ZoneMap,
+    resources: &Resources,
+) -> Result<TileMap, Box<dyn std::error::Error>> {
+    match map {
+        ZoneMap::Tiled(path) => TileMap::from(path),
+        ZoneMap::Dungeon(seed) => {
+            println!("Generating dungeon from seed {}", seed);
+            Ok(dungeon::generate(
+                *seed,
+                &DungeonSettings::default(),
+                &resources.get::<AutotileInfo>().unwrap(),
+            ))
+        }
+    }
+}
+
+/// Moves the world to a new zone, see [arrive]
+pub fn enter(
+    world: &mut World,
+    resources: &mut Resources,
+    destination: &Destination,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let map = load_map(&destination.map, resources)?;
+    arrive(world, resources, destination, map);
+    Ok(())
+}
+
+/// Moves the world to a new zone whose map is already loaded: the crops
+/// of the zone being left are put away in its farm, every entity that
+/// isn't [Global] is removed, the map is populated from its prefabs and
+/// has its farm and opened chests laid back over it, and the players are
+/// put at its entrance. Where they arrive becomes where they respawn.
+pub fn arrive(
+    world: &mut World,
+    resources: &mut Resources,
+    destination: &Destination,
+    mut map: TileMap,
+) {
+    {
+        let zone = resources.get::<Zone>().unwrap();
+        farming::store_crops(
+            world,
+            &mut resources.get_mut::<Farms>().unwrap(),
+            zone.map.key(),
+        );
+    }
+
+    let zone_entities: Vec<Entity> = <Entity>::query()
+        .filter(!component::<Global>())
+        .iter(world)
+        .copied()
+        .collect();
+    for entity in zone_entities {
+        world.remove(entity);
+    }
+
+    let arrival = destination
+        .entrance
+        .as_ref()
+        .and_then(|entrance| {
+            map.objects_of_kind("entrance")
+                .find(|object| &object.name == entrance)
+        })
+        .or_else(|| map.objects_of_kind("player_start").next())
+        .map_or((0, 0), |object| (object.x, object.y));
+
+    for (position, spawn, _) in
+        <(&mut Position, &mut SpawnPoint, &IsPlayerCharacter)>::query().iter_mut(world)
+    {
+        position.x = arrival.0;
+        position.y = arrival.1;
+        *spawn = SpawnPoint {
+            x: arrival.0,
+            y: arrival.1,
+        };
+    }
+
+    resources
+        .get::<PrefabInfo>()
+        .unwrap()
+        .spawn_from_map(world, &map);
+    loot::restore_chests(
+        world,
+        &resources.get::<OpenedChests>().unwrap(),
+        &destination.map,
+    );
+    farming::restore_farm(
+        world,
+        &mut resources.get_mut::<Farms>().unwrap(),
+        destination.map.key(),
+        &mut map,
+        &resources.get::<AutotileInfo>().unwrap(),
+        &resources.get::<FarmingInfo>().unwrap(),
+    );
+    resources.insert(map);
+
+    let mut zone = resources.get_mut::<Zone>().unwrap();
+    zone.map = destination.map.clone();
+    zone.transition = Transition::Arriving {
+        frames_left: FADE_FRAMES,
+    };
+    zone.armed = false;
+}
+
+/// Legion systems for moving between zones
+pub mod systems {
+    use super::*;
+
+    /// Starts heading to a portal's destination once a player steps into
+    /// it, unless a skill unlocks the portal and the player's isn't high enough
+    #[system]
+    #[read_component(IsPlayerCharacter)]
+    #[read_component(Position)]
+    #[read_component(Hurtbox)]
+    #[read_component(Skills)]
+    pub fn use_portals(
+        world: &SubWorld,
+        #[resource] map: &TileMap,
+        #[resource] zone: &mut Zone,
+        #[resource] rng: &mut Rng,
+        #[resource] skill_info: &SkillInfo,
+    ) {
+        if zone.transition != Transition::None {
+            return;
+        }
+
+        let portal = <(&Position, &Hurtbox, &Skills, &IsPlayerCharacter)>::query()
+            .iter(world)
+            .find_map(|(position, hurtbox, skills, _)| {
+                let body = hurtbox.at(position);
+                map.objects_of_kind("portal")
+                    .find(|portal| object_rect(portal).has_intersection(body))
+                    .map(|portal| {
+                        let unlock = Unlock::Area(portal.name.clone());
+                        (portal, skill_info.refusal(skills, &unlock))
+                    })
+            });
+
+        match portal {
+            Some((portal, Some(refusal))) if zone.armed => {
+                println!("{} is locked. {}", portal.name, refusal);
+                zone.armed = false;
+            }
+            Some((portal, None)) if zone.armed => match Destination::from_portal(portal, rng) {
+                Some(destination) => {
+                    zone.transition = Transition::Leaving {
+                        destination,
+                        frames_left: FADE_FRAMES,
+                    };
+                }
+                None => {
+                    println!("Portal {} doesn't lead anywhere", portal.name);
+                    zone.armed = false;
+                }
+            },
+            Some(_) => {}
+            None => zone.armed = true,
+        }
+    }
+
+    /// Fades the screen out and back in around a move between zones
+    #[system]
+    pub fn draw_transition(
+        #[resource] zone: &mut Zone,
+        #[resource] render_queue: &mut RenderQueue,
+    ) {
+        let opacity = match &mut zone.transition {
+            Transition::None => return,
+            Transition::Leaving { frames_left, .. } => {
+                *frames_left = frames_left.saturating_sub(1);
+                1.0 - *frames_left as f32 / FADE_FRAMES as f32
+            }
+            Transition::Arriving { frames_left } => {
+                *frames_left -= 1;
+                let opacity = *frames_left as f32 / FADE_FRAMES as f32;
+                if *frames_left == 0 {
+                    zone.transition = Transition::None;
+                }
+                opacity
+            }
+        };
+
+        render_queue.push(RenderRequest::Fade(opacity));
+    }
+}
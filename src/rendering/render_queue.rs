@@ -1,8 +1,17 @@
 use super::*;
 use crate::models::components::*;
+use crate::models::fishing::BAR_WIDTH;
 use sdl2::render::*;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// A quest's name, and the description, progress
+/// and target count of each of its objectives
+pub type TrackedQuest = (String, Vec<(String, u32, u32)>);
+
+/// A skill's name, level and experience, and the level
+/// and description of the next thing it unlocks
+pub type SkillRow = (String, u32, PrimaryStat, Option<(u32, String)>);
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum RenderRequest {
     Player {
         class: PlayerClass,
@@ -10,11 +19,139 @@ pub enum RenderRequest {
         position: Position,
         state: PlayerState,
     },
+    /// An entity drawn from the directional sprites named `sprite`.
+    /// Entities with health get a health bar above them once they're hurt.
+    Directional {
+        sprite: String,
+        position: Position,
+        state: PlayerState,
+        health: Option<f32>,
+    },
+    /// The `index`-th sprite of the sheet named `sheet`, drawn at (x, y)
+    Static {
+        sheet: String,
+        index: usize,
+        x: i32,
+        y: i32,
+    },
     HUD(PlayerClass, Gender, PlayerStats),
+    /// The icon and number of stacks of each status effect
+    /// on the player, drawn in a row beside the HUD portrait
+    StatusIcons(Vec<(usize, u32)>),
+    /// The icon of the player's gold and how much of it they have, under the status icons
+    Gold(usize, u32),
+    /// A projectile centred on (x, y), rotated clockwise by `angle` degrees
+    Projectile {
+        sprite: usize,
+        x: i32,
+        y: i32,
+        angle: f64,
+    },
+    /// A level-up flourish above the entity drawn at (x, y),
+    /// with a banner on the HUD announcing the new level
+    LevelUp {
+        x: i32,
+        y: i32,
+        level: u32,
+    },
+    /// The icon and remaining cooldown (as a fraction) of each ability on the hotbar
+    Hotbar([Option<(usize, f32)>; HOTBAR_SLOTS]),
+    /// The inventory screen: the icon and count of what's in each slot, the
+    /// icon of what's equipped in each equipment slot, and which is selected.
+    /// Selections past the end of `slots` are in the equipment column.
+    Inventory {
+        slots: Vec<Option<(usize, u32)>>,
+        equipment: [Option<usize>; EQUIPMENT_SLOTS.len()],
+        selected: usize,
+    },
+    /// A box at the bottom of the screen with what `speaker` is saying, of
+    /// which only the first `shown` characters are drawn, and the replies
+    /// the player can choose between with the `selected` one marked
+    Dialogue {
+        speaker: String,
+        text: String,
+        shown: usize,
+        choices: Vec<String>,
+        selected: usize,
+    },
+    /// Each of the player's active quests
+    QuestTracker(Vec<TrackedQuest>),
+    /// The shop screen: the icon, name, price and count of each row of
+    /// whichever list is being browsed, and how much gold the player has
+    Shop {
+        name: String,
+        selling: bool,
+        rows: Vec<(usize, String, u32, u32)>,
+        selected: usize,
+        gold: u32,
+    },
+    /// The skills page
+    Skills(Vec<SkillRow>),
+    /// The crafting menu: the icon, name and count of what each recipe that
+    /// can be made makes, and how many times it can be made
+    Crafting {
+        name: String,
+        rows: Vec<(usize, String, u32, u32)>,
+        selected: usize,
+    },
+    /// The save or load menu: a line for each slot, with the `selected` one marked
+    SaveMenu {
+        name: String,
+        rows: Vec<String>,
+        selected: usize,
+    },
+    /// A fishing line's bobber, centred on `bobber`, and the catching bar above
+    /// the player at `player` once something's hooked: where the marker is and
+    /// where the target starts and ends, out of [BAR_WIDTH](crate::models::fishing::BAR_WIDTH)
+    Fishing {
+        bobber: (i32, i32),
+        player: (i32, i32),
+        bar: Option<(i32, (i32, i32))>,
+    },
+    /// The day, counting from 1, and the time of day in the middle of the top of the screen
+    Clock {
+        day: u64,
+        hour: u32,
+        minute: u32,
+    },
+    /// The whole screen multiplied by the given colour, for the light at
+    /// different times of day. Whatever's drawn after it isn't tinted.
+    Tint((u8, u8, u8)),
+    /// Black drawn over the whole screen at the given opacity, from 0 to 1
+    Fade(f32),
 }
 
 pub type RenderQueue = Vec<RenderRequest>;
 
+/// Breaks text into lines of at most `width` characters, between words
+/// where possible
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+
+        // Words too long for a line of their own get split wherever they have to be
+        while line.chars().count() > width {
+            let rest = line.chars().skip(width).collect();
+            lines.push(line.chars().take(width).collect());
+            line = rest;
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
 pub fn render_queue_items<'a>(
     canvas: &mut WindowCanvas,
     render_queue: &mut RenderQueue,
@@ -36,6 +173,44 @@ pub fn render_queue_items<'a>(
 
                 animation.draw_animated(position.x, position.y, canvas);
             }
+            RenderRequest::Projectile {
+                sprite,
+                x,
+                y,
+                angle,
+            } => {
+                sprite_holder
+                    .get_sprite("projectiles")
+                    .draw_rotated(sprite, x, y, angle, canvas);
+            }
+            RenderRequest::Directional {
+                sprite,
+                position,
+                state,
+                health,
+            } => {
+                sprite_holder
+                    .get_directional(&sprite, state as usize, position.direction)
+                    .draw_animated(position.x, position.y, canvas);
+
+                let health = health.unwrap_or(1.0);
+                if health < 1.0 && state != PlayerState::Death {
+                    let (x, y) = (position.x, position.y - 4);
+                    canvas.set_draw_color(sdl2::pixels::Color::RGB(60, 0, 0));
+                    canvas
+                        .fill_rect(sdl2::rect::Rect::new(x, y, 16, 2))
+                        .unwrap();
+                    canvas.set_draw_color(sdl2::pixels::Color::RGB(200, 30, 30));
+                    canvas
+                        .fill_rect(sdl2::rect::Rect::new(x, y, (16.0 * health) as u32, 2))
+                        .unwrap();
+                }
+            }
+            RenderRequest::Static { sheet, index, x, y } => {
+                sprite_holder
+                    .get_sprite(&sheet)
+                    .draw_to(index, x, y, canvas);
+            }
             RenderRequest::HUD(class, gender, stats) => {
                 sprite_holder
                     .get_sprite(format!("{}_{}_ui", class.to_string(), gender.to_string()).as_str())
@@ -58,6 +233,500 @@ pub fn render_queue_items<'a>(
                     .get_sprite("exp_bar")
                     .draw_portion_of(0, 49, 35, exp_pct, 1.0, canvas);
             }
+            RenderRequest::StatusIcons(effects) => {
+                let icons = sprite_holder.get_sprite("status_icons");
+                let digits = sprite_holder.get_sprite("digits");
+
+                for (i, (icon, stacks)) in effects.iter().enumerate() {
+                    let x = 106 + i as i32 * 14;
+                    icons.draw_to(*icon, x, 2, canvas);
+                    if *stacks > 1 {
+                        digits.draw_number(*stacks, x + 8, 9, canvas);
+                    }
+                }
+            }
+            RenderRequest::Gold(icon, amount) => {
+                sprite_holder
+                    .get_sprite("items")
+                    .draw_to(icon, 104, 16, canvas);
+                sprite_holder
+                    .get_sprite("digits")
+                    .draw_number(amount, 122, 21, canvas);
+            }
+            RenderRequest::LevelUp { x, y, level } => {
+                sprite_holder
+                    .get_sprite_mut("level_up")
+                    .draw_animated(x, y - 16, canvas);
+
+                sprite_holder
+                    .get_sprite("level_up_banner")
+                    .draw_to(0, 0, 48, canvas);
+                sprite_holder
+                    .get_sprite("digits")
+                    .draw_number(level, 68, 52, canvas);
+            }
+            RenderRequest::Hotbar(slots) => {
+                let slot_sprite = sprite_holder.get_sprite("hotbar_slot");
+                let icons = sprite_holder.get_sprite("ability_icons");
+                let (slot_width, slot_height) = (20, 20);
+
+                // Centre the hotbar along the bottom of the (scaled) screen
+                let left = (WINDOW_WIDTH / 4) as i32 - (slot_width * HOTBAR_SLOTS as i32) / 2;
+                let top = (WINDOW_HEIGHT / 2) as i32 - slot_height - 4;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 160));
+
+                for (i, slot) in slots.iter().enumerate() {
+                    let x = left + i as i32 * slot_width;
+                    slot_sprite.draw_to(0, x, top, canvas);
+
+                    if let Some((icon, cooldown)) = slot {
+                        icons.draw_to(*icon, x + 2, top + 2, canvas);
+
+                        // Shade the icon from the top down while it's on cooldown
+                        let shade = (16.0 * cooldown) as u32;
+                        if shade > 0 {
+                            canvas
+                                .fill_rect(sdl2::rect::Rect::new(x + 2, top + 2, 16, shade))
+                                .unwrap();
+                        }
+                    }
+                }
+            }
+            RenderRequest::Inventory {
+                slots,
+                equipment,
+                selected,
+            } => {
+                let slot_sprite = sprite_holder.get_sprite("hotbar_slot");
+                let icons = sprite_holder.get_sprite("items");
+                let digits = sprite_holder.get_sprite("digits");
+                let (slot_width, slot_height) = (20, 20);
+                let rows = slots.len().div_ceil(INVENTORY_COLUMNS);
+
+                // Centre the grid on the (scaled) screen
+                let (width, height) = (
+                    slot_width * INVENTORY_COLUMNS as i32,
+                    slot_height * rows as i32,
+                );
+                let left = (WINDOW_WIDTH / 4) as i32 - width / 2;
+                let top = (WINDOW_HEIGHT / 4) as i32 - height / 2;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 160));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left - 4,
+                        top - 4,
+                        width as u32 + 8,
+                        height as u32 + 8,
+                    ))
+                    .unwrap();
+
+                for (i, slot) in slots.iter().enumerate() {
+                    let x = left + (i % INVENTORY_COLUMNS) as i32 * slot_width;
+                    let y = top + (i / INVENTORY_COLUMNS) as i32 * slot_height;
+                    slot_sprite.draw_to(0, x, y, canvas);
+
+                    if let Some((icon, count)) = slot {
+                        icons.draw_to(*icon, x + 2, y + 2, canvas);
+                        if *count > 1 {
+                            let digit_count = count.to_string().len() as i32;
+                            digits.draw_number(*count, x + 18 - digit_count * 5, y + 11, canvas);
+                        }
+                    }
+                }
+
+                // Equipment goes in a column of its own to the left of the grid
+                let equipment_left = left - slot_width - 8;
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 160));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        equipment_left - 4,
+                        top - 4,
+                        slot_width as u32 + 8,
+                        (slot_height * equipment.len() as i32) as u32 + 8,
+                    ))
+                    .unwrap();
+
+                for (i, icon) in equipment.iter().enumerate() {
+                    let y = top + i as i32 * slot_height;
+                    slot_sprite.draw_to(0, equipment_left, y, canvas);
+
+                    if let Some(icon) = icon {
+                        icons.draw_to(*icon, equipment_left + 2, y + 2, canvas);
+                    }
+                }
+
+                let (x, y) = match selected.checked_sub(slots.len()) {
+                    Some(row) => (equipment_left, top + row as i32 * slot_height),
+                    None => (
+                        left + (selected % INVENTORY_COLUMNS) as i32 * slot_width,
+                        top + (selected / INVENTORY_COLUMNS) as i32 * slot_height,
+                    ),
+                };
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 255, 255));
+                canvas
+                    .draw_rect(sdl2::rect::Rect::new(
+                        x,
+                        y,
+                        slot_width as u32,
+                        slot_height as u32,
+                    ))
+                    .unwrap();
+            }
+            RenderRequest::Dialogue {
+                speaker,
+                text,
+                shown,
+                choices,
+                selected,
+            } => {
+                let font = sprite_holder.get_sprite("font");
+                let (glyph_width, line_height) = (6, 10);
+
+                // The box spans the (scaled) screen, just above the hotbar
+                let width = (WINDOW_WIDTH / 2) as i32 - 16;
+                let lines = wrap(&text, (width as usize - 8) / glyph_width as usize);
+                let height = line_height * (2 + lines.len() + choices.len()) as i32 + 8;
+                let (left, top) = (8, (WINDOW_HEIGHT / 2) as i32 - 32 - height);
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 200));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left,
+                        top,
+                        width as u32,
+                        height as u32,
+                    ))
+                    .unwrap();
+
+                font.draw_text(&speaker, left + 4, top + 4, canvas);
+
+                // Lines are wrapped as a whole before they're revealed,
+                // so that words don't jump down a line halfway through
+                let mut left_to_show = shown;
+                for (i, line) in lines.iter().enumerate() {
+                    let revealed: String = line.chars().take(left_to_show).collect();
+                    left_to_show = left_to_show.saturating_sub(line.chars().count() + 1);
+                    font.draw_text(
+                        &revealed,
+                        left + 4,
+                        top + 4 + line_height * (i as i32 + 1),
+                        canvas,
+                    );
+                }
+
+                let choices_top = top + 4 + line_height * (lines.len() as i32 + 2);
+                for (i, choice) in choices.iter().enumerate() {
+                    let y = choices_top + line_height * i as i32;
+                    if i == selected {
+                        font.draw_text(">", left + 4, y, canvas);
+                    }
+                    font.draw_text(choice, left + 4 + glyph_width * 2, y, canvas);
+                }
+            }
+            RenderRequest::QuestTracker(quests) => {
+                let font = sprite_holder.get_sprite("font");
+                let (glyph_width, line_height) = (6, 10);
+
+                // Down the right-hand side of the (scaled) screen
+                let width = 28 * glyph_width;
+                let lines: i32 = quests
+                    .iter()
+                    .map(|(_, objectives)| 1 + objectives.len() as i32)
+                    .sum();
+                let (left, top) = ((WINDOW_WIDTH / 2) as i32 - width - 4, 4);
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 120));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left,
+                        top,
+                        width as u32,
+                        (lines * line_height + 4) as u32,
+                    ))
+                    .unwrap();
+
+                let mut y = top + 3;
+                for (name, objectives) in quests.iter() {
+                    font.draw_text(name, left + 3, y, canvas);
+                    y += line_height;
+
+                    for (description, progress, count) in objectives.iter() {
+                        let line = if progress >= count {
+                            format!(" + {}", description)
+                        } else if *count > 1 {
+                            format!(" - {} {}/{}", description, progress, count)
+                        } else {
+                            format!(" - {}", description)
+                        };
+                        font.draw_text(&line, left + 3, y, canvas);
+                        y += line_height;
+                    }
+                }
+            }
+            RenderRequest::Shop {
+                name,
+                selling,
+                rows,
+                selected,
+                gold,
+            } => {
+                let font = sprite_holder.get_sprite("font");
+                let icons = sprite_holder.get_sprite("items");
+                let digits = sprite_holder.get_sprite("digits");
+                let (glyph_width, row_height) = (6, 18);
+
+                // Centre the shop on the (scaled) screen
+                let (width, height) = (220, row_height * rows.len().max(1) as i32 + 44);
+                let left = (WINDOW_WIDTH / 4) as i32 - width / 2;
+                let top = (WINDOW_HEIGHT / 4) as i32 - height / 2;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 200));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left,
+                        top,
+                        width as u32,
+                        height as u32,
+                    ))
+                    .unwrap();
+
+                font.draw_text(&name, left + 4, top + 4, canvas);
+                let tabs = if selling {
+                    " Buy  [Sell]"
+                } else {
+                    "[Buy]  Sell "
+                };
+                font.draw_text(
+                    tabs,
+                    left + width - 4 - glyph_width * tabs.len() as i32,
+                    top + 4,
+                    canvas,
+                );
+
+                let rows_top = top + 18;
+                if rows.is_empty() {
+                    font.draw_text("Nothing here.", left + 8, rows_top + 4, canvas);
+                }
+
+                for (i, (icon, item, price, count)) in rows.iter().enumerate() {
+                    let y = rows_top + i as i32 * row_height;
+                    if i == selected {
+                        font.draw_text(">", left + 4, y + 4, canvas);
+                    }
+
+                    icons.draw_to(*icon, left + 12, y, canvas);
+                    font.draw_text(item, left + 32, y + 4, canvas);
+                    digits.draw_number(*price, left + width - 80, y + 5, canvas);
+                    font.draw_text(&format!("x{}", count), left + width - 36, y + 4, canvas);
+                }
+
+                font.draw_text("Gold", left + 4, top + height - 14, canvas);
+                digits.draw_number(gold, left + 34, top + height - 13, canvas);
+            }
+            RenderRequest::Skills(rows) => {
+                use sdl2::pixels::Color;
+                use sdl2::rect::Rect;
+
+                let font = sprite_holder.get_sprite("font");
+                let digits = sprite_holder.get_sprite("digits");
+                let (bar_width, row_height) = (100, 30);
+
+                // Centre the page on the (scaled) screen
+                let (width, height) = (220, row_height * rows.len() as i32 + 22);
+                let left = (WINDOW_WIDTH / 4) as i32 - width / 2;
+                let top = (WINDOW_HEIGHT / 4) as i32 - height / 2;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+                canvas
+                    .fill_rect(Rect::new(left, top, width as u32, height as u32))
+                    .unwrap();
+                font.draw_text("Skills", left + 4, top + 4, canvas);
+
+                for (i, (name, level, experience, next)) in rows.iter().enumerate() {
+                    let y = top + 18 + i as i32 * row_height;
+                    font.draw_text(name, left + 8, y, canvas);
+                    font.draw_text("Lv", left + 64, y, canvas);
+                    digits.draw_number(*level, left + 78, y + 1, canvas);
+
+                    // The experience bar fills up towards the next level
+                    let bar_left = left + width - 8 - bar_width;
+                    canvas.set_draw_color(Color::RGB(40, 40, 40));
+                    canvas
+                        .fill_rect(Rect::new(bar_left, y + 1, bar_width as u32, 5))
+                        .unwrap();
+                    canvas.set_draw_color(Color::RGB(90, 160, 230));
+                    let filled = (bar_width as f32 * experience.as_percent().min(1.0)) as u32;
+                    if filled > 0 {
+                        canvas
+                            .fill_rect(Rect::new(bar_left, y + 1, filled, 5))
+                            .unwrap();
+                    }
+
+                    if let Some((level, description)) = next {
+                        let text = format!("Lv {}: {}", level, description);
+                        font.draw_text(&text, left + 16, y + 12, canvas);
+                    }
+                }
+            }
+            RenderRequest::Crafting {
+                name,
+                rows,
+                selected,
+            } => {
+                let font = sprite_holder.get_sprite("font");
+                let icons = sprite_holder.get_sprite("items");
+                let row_height = 18;
+
+                // Centre the menu on the (scaled) screen
+                let (width, height) = (220, row_height * rows.len().max(1) as i32 + 26);
+                let left = (WINDOW_WIDTH / 4) as i32 - width / 2;
+                let top = (WINDOW_HEIGHT / 4) as i32 - height / 2;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 200));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left,
+                        top,
+                        width as u32,
+                        height as u32,
+                    ))
+                    .unwrap();
+
+                font.draw_text(&name, left + 4, top + 4, canvas);
+
+                let rows_top = top + 18;
+                if rows.is_empty() {
+                    font.draw_text("Nothing you can make.", left + 8, rows_top + 4, canvas);
+                }
+
+                for (i, (icon, item, count, batches)) in rows.iter().enumerate() {
+                    let y = rows_top + i as i32 * row_height;
+                    if i == selected {
+                        font.draw_text(">", left + 4, y + 4, canvas);
+                    }
+
+                    icons.draw_to(*icon, left + 12, y, canvas);
+                    let item = if *count > 1 {
+                        format!("{} x{}", item, count)
+                    } else {
+                        item.clone()
+                    };
+                    font.draw_text(&item, left + 32, y + 4, canvas);
+                    font.draw_text(&format!("x{}", batches), left + width - 36, y + 4, canvas);
+                }
+            }
+            RenderRequest::SaveMenu {
+                name,
+                rows,
+                selected,
+            } => {
+                let font = sprite_holder.get_sprite("font");
+                let row_height = 12;
+
+                // Centre the menu on the (scaled) screen
+                let (width, height) = (200, row_height * rows.len() as i32 + 24);
+                let left = (WINDOW_WIDTH / 4) as i32 - width / 2;
+                let top = (WINDOW_HEIGHT / 4) as i32 - height / 2;
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 200));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        left,
+                        top,
+                        width as u32,
+                        height as u32,
+                    ))
+                    .unwrap();
+
+                font.draw_text(&name, left + 4, top + 4, canvas);
+                for (i, row) in rows.iter().enumerate() {
+                    let y = top + 18 + i as i32 * row_height;
+                    if i == selected {
+                        font.draw_text(">", left + 4, y, canvas);
+                    }
+                    font.draw_text(row, left + 16, y, canvas);
+                }
+            }
+            RenderRequest::Fishing {
+                bobber,
+                player,
+                bar,
+            } => {
+                // The bobber dips under once something bites
+                sprite_holder.get_sprite("bobber").draw_to(
+                    bar.is_some() as usize,
+                    bobber.0 - 4,
+                    bobber.1 - 4,
+                    canvas,
+                );
+
+                if let Some((marker, (start, end))) = bar {
+                    use sdl2::pixels::Color;
+                    use sdl2::rect::Rect;
+
+                    // The bar's drawn at half size, centred above the player
+                    let (left, top) = (player.0 + 8 - BAR_WIDTH / 4, player.1 - 12);
+
+                    canvas.set_blend_mode(BlendMode::Blend);
+                    canvas.set_draw_color(Color::RGBA(0, 0, 0, 200));
+                    canvas
+                        .fill_rect(Rect::new(left - 1, top - 1, (BAR_WIDTH / 2 + 2) as u32, 6))
+                        .unwrap();
+                    canvas.set_draw_color(Color::RGB(90, 200, 90));
+                    canvas
+                        .fill_rect(Rect::new(
+                            left + start / 2,
+                            top,
+                            ((end - start) / 2).max(1) as u32,
+                            4,
+                        ))
+                        .unwrap();
+                    canvas.set_draw_color(Color::RGB(255, 255, 255));
+                    canvas
+                        .fill_rect(Rect::new(left + marker / 2, top - 1, 1, 6))
+                        .unwrap();
+                }
+            }
+            RenderRequest::Clock { day, hour, minute } => {
+                let font = sprite_holder.get_sprite("font");
+                let text = format!("Day {} {:02}:{:02}", day, hour, minute);
+
+                // In the middle of the top of the (scaled) screen
+                let width = text.chars().count() as i32 * 6 + 6;
+                let (left, top) = ((WINDOW_WIDTH / 4) as i32 - width / 2, 4);
+
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 120));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(left, top, width as u32, 14))
+                    .unwrap();
+                font.draw_text(&text, left + 3, top + 3, canvas);
+            }
+            RenderRequest::Tint((r, g, b)) => {
+                canvas.set_blend_mode(BlendMode::Mod);
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(r, g, b));
+                canvas.fill_rect(None).unwrap();
+            }
+            RenderRequest::Fade(opacity) => {
+                canvas.set_blend_mode(BlendMode::Blend);
+                canvas.set_draw_color(sdl2::pixels::Color::RGBA(
+                    0,
+                    0,
+                    0,
+                    (255.0 * opacity.clamp(0.0, 1.0)) as u8,
+                ));
+                canvas.fill_rect(None).unwrap();
+            }
         }
     }
 }
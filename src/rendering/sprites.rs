@@ -1,3 +1,4 @@
+use crate::map::TileMap;
 use crate::models::components::*;
 use crate::util::*;
 use sdl2::image::*;
@@ -106,47 +107,91 @@ impl<'a> SpriteSheet<'a> {
         self.animation_counter += 1;
     }
 
-    /// Assumes that the sprite-sheet corresponds to a tileset being used
-    /// to draw a tilemap, and draws a tilemap given the path to a .tmx (tiled)
-    /// file.
-    pub fn draw_map(&self, canvas: &mut WindowCanvas, tilemap_path: &str) {
-        // There's some funkiness with how .tmx files work that's show-cased in this
-        // function
-        let tilemap = tiled::parse_file(std::path::Path::new(tilemap_path)).unwrap();
-
-        // We should really only ever have maps with finite layer-data
-        // See [tiled::LayerData](https://docs.rs/tiled/latest/tiled/enum.LayerData.html)
-        // for more info on what this is
-        let layer_data = match &tilemap.layers[0].tiles {
-            tiled::LayerData::Finite(tiles) => tiles,
-            _ => {
-                panic!("Ooops");
-            }
-        };
+    /// Draws the i-th sprite on the sheet centred on (x, y) and
+    /// rotated clockwise by `angle` degrees. Useful for things like
+    /// projectiles that can face any direction.
+    pub fn draw_rotated(&self, i: usize, x: i32, y: i32, angle: f64, canvas: &mut WindowCanvas) {
+        let sprite = self.sprites[i];
+        let (width, height) = self.sprite_dimensions;
+        let dst = sdl2::rect::Rect::new(
+            x - (width / 2) as i32,
+            y - (height / 2) as i32,
+            width,
+            height,
+        );
+        canvas
+            .copy_ex(&self.texture, sprite, dst, angle, None, false, false)
+            .unwrap();
+    }
+
+    /// Assumes that the sheet holds the digits 0 to 9 in order and
+    /// draws `number` with its leftmost digit at (x, y)
+    pub fn draw_number(&self, number: u32, x: i32, y: i32, canvas: &mut WindowCanvas) {
+        let digit_width = self.sprite_dimensions.0 as i32;
+
+        for (i, digit) in number.to_string().chars().enumerate() {
+            self.draw_to(
+                digit.to_digit(10).unwrap() as usize,
+                x + i as i32 * digit_width,
+                y,
+                canvas,
+            );
+        }
+    }
+
+    /// Assumes that the sheet holds the printable ASCII characters in
+    /// order, starting from the space, and draws `text` on a single line
+    /// starting at (x, y). Anything unprintable is drawn as a `?`.
+    pub fn draw_text(&self, text: &str, x: i32, y: i32, canvas: &mut WindowCanvas) {
+        let glyph_width = self.sprite_dimensions.0 as i32;
 
+        for (i, character) in text.chars().enumerate() {
+            let character = if (' '..='~').contains(&character) {
+                character
+            } else {
+                '?'
+            };
+
+            self.draw_to(
+                character as usize - ' ' as usize,
+                x + i as i32 * glyph_width,
+                y,
+                canvas,
+            );
+        }
+    }
+
+    /// Assumes that the sprite-sheet corresponds to a tileset being used
+    /// to draw a tilemap, and draws every layer of the given [TileMap].
+    pub fn draw_map(&self, canvas: &mut WindowCanvas, tilemap: &TileMap) {
         let (sprite_width, sprite_height) = self.sprite_dimensions;
+        let columns = tilemap.width.min(WINDOW_WIDTH / sprite_width);
+        let rows = tilemap.height.min(WINDOW_HEIGHT / sprite_height);
 
-        // .tmx files store coordinates in (y, x) where each starts at
-        // the top left corner and moves down and to the right.
-        for j in 0..(WINDOW_HEIGHT / sprite_height) {
-            for i in 0..(WINDOW_WIDTH / sprite_width) {
-                // In an ideal world, the "group-id" of each tile
-                // in the layer data corresponds to a sprite in this
-                // sprite-sheet. However, there is some nuance that
-                // gets introduced when you have two tilesets being
-                // used to produce one tilemap, and thus we actually
-                // have to find the first gid in the tileset and
-                // subtract that gid from each gid in the layer data
-                // before we know we have a sprite in our sheet.
-                let gid = layer_data[j as usize][i as usize].gid;
-                let tileset = tilemap.get_tileset_by_gid(gid).unwrap();
-
-                self.draw_to(
-                    (gid - tileset.first_gid) as usize,
-                    (i * sprite_width) as i32,
-                    (j * sprite_height) as i32,
-                    canvas,
-                );
+        for layer in tilemap.layers.iter() {
+            // Maps store coordinates in (y, x) where each starts at
+            // the top left corner and moves down and to the right.
+            for j in 0..rows {
+                for i in 0..columns {
+                    // A gid of 0 means there's no tile here on this layer
+                    let gid = layer[j as usize][i as usize];
+                    if gid == 0 {
+                        continue;
+                    }
+
+                    // In an ideal world, the "group-id" of each tile
+                    // in the layer data corresponds to a sprite in this
+                    // sprite-sheet. However, tiled numbers tiles starting
+                    // from the first gid of the tileset, so we have to
+                    // subtract that gid from each gid in the layer data
+                    // before we know we have a sprite in our sheet.
+                    self.draw_to(
+                        (gid - tilemap.first_gid) as usize,
+                        (i * sprite_width) as i32,
+                        (j * sprite_height) as i32,
+                        canvas,
+                    );
+                }
             }
         }
     }
@@ -301,6 +346,16 @@ impl<'a> SpriteHolder<'a> {
         self.sprite_map.get(name).unwrap()
     }
 
+    /// Returns a mutable reference to a sprite with the given name,
+    /// for drawing the sprite as an animation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a sprite with the given name doesn't exist in the `sprite_map`.
+    pub fn get_sprite_mut(&mut self, name: &str) -> &mut SpriteSheet<'a> {
+        self.sprite_map.get_mut(name).unwrap()
+    }
+
     /// Returns a mutable reference to the given directional variant
     /// of the sprite with the given name.
     ///
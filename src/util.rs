@@ -1,4 +1,6 @@
+use crate::models::components::{PlayerClass, StatBonus, Status};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize)]
 pub struct Config {
@@ -56,3 +58,490 @@ impl TileSheet {
         Ok(tile_sheet)
     }
 }
+
+/// The shape and timing of a melee attack. Frames are counted
+/// from the moment the attack starts.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct MeleeAttackInfo {
+    /// How far from the attacker the hitbox extends, in pixels
+    pub reach: u32,
+    /// The width of the hitbox wedge, in degrees
+    pub arc: f32,
+    pub damage: u32,
+    /// Frames after the attack ends before another may start
+    pub cooldown: u32,
+    /// The total length of the attack in frames
+    pub duration: u32,
+    /// The first and last frames on which the hitbox is live
+    pub active_frames: (u32, u32),
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AttackInfo {
+    pub attacks: HashMap<PlayerClass, MeleeAttackInfo>,
+}
+
+impl AttackInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let attack_info = ron::from_str(&contents)?;
+        Ok(attack_info)
+    }
+
+    /// Returns the melee attack for the given class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class has no attack defined.
+    pub fn get(&self, class: PlayerClass) -> &MeleeAttackInfo {
+        self.attacks.get(&class).unwrap()
+    }
+}
+
+/// A single strike in a melee combo
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ComboStrike {
+    pub reach: u32,
+    pub arc: f32,
+    pub damage: u32,
+    /// How long the strike locks the attacker in place, in frames
+    pub duration: u32,
+}
+
+/// A burst of damage centred on the point a projectile lands
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExplosionInfo {
+    pub radius: u32,
+    pub damage: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ProjectileInfo {
+    /// Index of the projectile's sprite in the `projectiles` sprite-sheet.
+    /// Sprites are drawn pointing to the right and rotated to match the
+    /// projectile's heading.
+    pub sprite: usize,
+    /// How far the projectile travels each frame, in pixels
+    pub speed: i32,
+    pub damage: u32,
+    /// How many frames the projectile flies for before it lands
+    pub lifetime: u32,
+    /// How many targets the projectile passes through before
+    /// it stops at the next one
+    #[serde(default)]
+    pub pierce: u32,
+    /// Set for projectiles that explode wherever they land
+    #[serde(default)]
+    pub explosion: Option<ExplosionInfo>,
+    /// The status effect inflicted on whatever the projectile or its explosion hits
+    #[serde(default)]
+    pub status: Option<Status>,
+}
+
+/// What happens when an ability is used
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum AbilityEffect {
+    /// Fires a projectile in the caster's facing direction
+    Projectile(ProjectileInfo),
+    /// Strikes everything within `radius` pixels of the caster,
+    /// inflicting `status` on them if it's set
+    Nova {
+        radius: u32,
+        damage: u32,
+        #[serde(default)]
+        status: Option<Status>,
+    },
+    /// A chain of melee strikes, continued by using the ability
+    /// again within `window` frames of the previous strike ending.
+    /// Kits with a combo of no strikes are refused when loaded.
+    Combo {
+        strikes: Vec<ComboStrike>,
+        window: u32,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AbilityInfo {
+    pub name: String,
+    /// Index of the ability's icon in the `ability_icons` sprite-sheet
+    pub icon: usize,
+    pub mana_cost: u32,
+    pub cooldown: u32,
+    /// How long casting locks the caster in place, in frames.
+    /// Combos use the duration of each strike instead.
+    pub cast_frames: u32,
+    pub effect: AbilityEffect,
+}
+
+/// The abilities available to each class, in hotbar order
+#[derive(Deserialize, Serialize)]
+pub struct KitInfo {
+    pub kits: HashMap<PlayerClass, Vec<AbilityInfo>>,
+}
+
+impl KitInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let kit_info: KitInfo = ron::from_str(&contents)?;
+
+        // Combos are used a strike at a time, so they need at least one
+        for ability in kit_info.kits.values().flatten() {
+            if let AbilityEffect::Combo { strikes, .. } = &ability.effect {
+                if strikes.is_empty() {
+                    return Err(format!("the {} combo has no strikes", ability.name).into());
+                }
+            }
+        }
+
+        Ok(kit_info)
+    }
+
+    /// Returns the abilities of the given class, which
+    /// is empty if the class has no kit defined.
+    pub fn kit(&self, class: PlayerClass) -> &[AbilityInfo] {
+        self.kits.get(&class).map_or(&[], |kit| kit.as_slice())
+    }
+}
+
+/// How much experience each level takes to complete, following
+/// `base * level ^ exponent`
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct ExperienceCurve {
+    pub base: u32,
+    pub exponent: f32,
+}
+
+impl ExperienceCurve {
+    /// The experience needed to advance past the given level
+    pub fn required(&self, level: u32) -> u32 {
+        (self.base as f32 * (level as f32).powf(self.exponent)).round() as u32
+    }
+}
+
+/// How much a class's stats grow by each level
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct StatGrowth {
+    pub health: u32,
+    pub mana: u32,
+    #[serde(default)]
+    pub attack: u32,
+    #[serde(default)]
+    pub defence: u32,
+}
+
+/// A class's stats at level one
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct BaseStats {
+    pub health: u32,
+    pub mana: u32,
+    pub attack: u32,
+    pub defence: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProgressionInfo {
+    pub experience_curve: ExperienceCurve,
+    pub base: HashMap<PlayerClass, BaseStats>,
+    pub growth: HashMap<PlayerClass, StatGrowth>,
+}
+
+impl ProgressionInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let progression_info = ron::from_str(&contents)?;
+        Ok(progression_info)
+    }
+
+    /// Returns the per-level stat growth of the given class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class has no growth defined.
+    pub fn growth(&self, class: PlayerClass) -> &StatGrowth {
+        self.growth.get(&class).unwrap()
+    }
+
+    /// Returns the level one stats of the given class.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class has no base stats defined.
+    pub fn base(&self, class: PlayerClass) -> &BaseStats {
+        self.base.get(&class).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ItemCategory {
+    Consumable,
+    Weapon,
+    Armor,
+    Accessory,
+    Material,
+    /// Planted on tilled soil to grow a crop
+    Seed,
+    /// Money, which goes straight into its collector's purse
+    /// rather than their inventory
+    Currency,
+}
+
+/// What an item does for whoever uses it
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ItemStats {
+    /// Health and mana restored when the item is consumed
+    pub health: u32,
+    pub mana: u32,
+    /// Bonuses to the stats of whoever has the item equipped
+    pub attack: i32,
+    pub defence: i32,
+    pub max_health: i32,
+    pub max_mana: i32,
+}
+
+impl ItemStats {
+    pub fn bonus(&self) -> StatBonus {
+        StatBonus {
+            attack: self.attack,
+            defence: self.defence,
+            max_health: self.max_health,
+            max_mana: self.max_mana,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ItemDef {
+    pub name: String,
+    /// Index of the item's icon in the `items` sprite-sheet
+    pub icon: usize,
+    /// The most of the item that fits in one inventory slot
+    pub stack_size: u32,
+    pub category: ItemCategory,
+    /// What shops charge for the item, unless they set their own price.
+    /// Items worth nothing can't be sold.
+    #[serde(default)]
+    pub value: u32,
+    #[serde(default)]
+    pub stats: ItemStats,
+    /// The classes that can equip the item. Anyone can if it's empty.
+    #[serde(default)]
+    pub classes: Vec<PlayerClass>,
+    /// The status effect a consumable inflicts on whoever uses it
+    #[serde(default)]
+    pub status: Option<Status>,
+}
+
+impl ItemDef {
+    pub fn usable_by(&self, class: PlayerClass) -> bool {
+        self.classes.is_empty() || self.classes.contains(&class)
+    }
+}
+
+/// Every item, by id
+#[derive(Deserialize, Serialize)]
+pub struct ItemInfo {
+    pub items: HashMap<String, ItemDef>,
+}
+
+impl ItemInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let item_info = ron::from_str(&contents)?;
+        Ok(item_info)
+    }
+
+    pub fn get(&self, item: &str) -> Option<&ItemDef> {
+        self.items.get(item)
+    }
+}
+
+/// What happens when a status effect is inflicted on a
+/// character who already has it
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Stacking {
+    /// The effect's duration starts over
+    Refresh,
+    /// The effect's full duration is added to what's left of it
+    Extend,
+    /// The effect gains a stack, up to the given number, and its
+    /// duration starts over. Each stack applies the effect again.
+    Intensify(u32),
+}
+
+/// What a status effect does. Effects can do any combination of these.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct StatusEffectInfo {
+    /// Index of the effect's icon in the `status_icons` sprite-sheet
+    pub icon: usize,
+    /// How long the effect lasts, in frames
+    pub duration: u32,
+    pub stacking: Stacking,
+    /// How often the effect deals damage or heals, in frames
+    #[serde(default)]
+    pub interval: u32,
+    /// Damage dealt each interval
+    #[serde(default)]
+    pub damage: u32,
+    /// Health restored each interval
+    #[serde(default)]
+    pub heal: u32,
+    /// How much slower the effect makes its target move, as a percentage
+    #[serde(default)]
+    pub slow: u32,
+    /// Bonuses to the stats of the effect's target
+    #[serde(default)]
+    pub bonus: StatBonus,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct StatusInfo {
+    pub effects: HashMap<Status, StatusEffectInfo>,
+}
+
+impl StatusInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let status_info = ron::from_str(&contents)?;
+        Ok(status_info)
+    }
+
+    /// Returns what the given status effect does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the status effect isn't defined.
+    pub fn get(&self, status: Status) -> &StatusEffectInfo {
+        self.effects.get(&status).unwrap()
+    }
+}
+
+fn one_of_each() -> (u32, u32) {
+    (1, 1)
+}
+
+/// One of the outcomes of a roll on a loot table
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LootEntry {
+    /// The item dropped, or `None` for a roll that drops nothing
+    pub item: Option<String>,
+    /// How likely this entry is relative to the others in its table
+    pub weight: u32,
+    /// The fewest and most of the item dropped
+    #[serde(default = "one_of_each")]
+    pub count: (u32, u32),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LootTableInfo {
+    /// How many times the table is rolled
+    pub rolls: u32,
+    /// The least and most gold dropped alongside the items
+    #[serde(default)]
+    pub gold: (u32, u32),
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootTableInfo {
+    /// Rolls the table, returning the items dropped and how many of each
+    pub fn roll(&self, rng: &mut Rng) -> Vec<(String, u32)> {
+        let total: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        let mut drops = vec![];
+
+        for _ in 0..self.rolls {
+            if total == 0 {
+                break;
+            }
+
+            let mut pick = (rng.next_u64() % total as u64) as u32;
+            let entry = self
+                .entries
+                .iter()
+                .find(|entry| {
+                    if pick < entry.weight {
+                        true
+                    } else {
+                        pick -= entry.weight;
+                        false
+                    }
+                })
+                .unwrap();
+
+            if let Some(item) = &entry.item {
+                let (fewest, most) = entry.count;
+                let count = rng.range(fewest as i32, most as i32 + 1) as u32;
+                if count > 0 {
+                    drops.push((item.clone(), count));
+                }
+            }
+        }
+
+        drops
+    }
+
+    /// Rolls how much gold drops
+    pub fn roll_gold(&self, rng: &mut Rng) -> u32 {
+        let (least, most) = self.gold;
+        rng.range(least as i32, most as i32 + 1) as u32
+    }
+}
+
+/// Every loot table, by name
+#[derive(Deserialize, Serialize)]
+pub struct LootInfo {
+    pub tables: HashMap<String, LootTableInfo>,
+}
+
+impl LootInfo {
+    pub fn from(file: &'static str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(file)?;
+        let loot_info = ron::from_str(&contents)?;
+        Ok(loot_info)
+    }
+
+    pub fn get(&self, table: &str) -> Option<&LootTableInfo> {
+        self.tables.get(table)
+    }
+}
+
+/// A small, seedable xorshift random number generator. Anything
+/// that should be reproducible from a seed should draw from one of
+/// these rather than from a source of true randomness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on a state of zero, so scramble the
+        // seed with an arbitrary odd constant to avoid it
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Rng {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    /// Produces a generator seeded from the current time
+    pub fn from_time() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self::new(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in the range [low, high). Returns `low` if the range is empty.
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+        low + (self.next_u64() % (high - low) as u64) as i32
+    }
+}